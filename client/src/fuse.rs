@@ -9,37 +9,454 @@ use libc::EIO;
 use chrono::{DateTime};
 use tokio::task;
 use fuser::{FileAttr, FileType, Filesystem, ReplyAttr, ReplyCreate, ReplyData, ReplyDirectory, ReplyEmpty, ReplyEntry, ReplyOpen, ReplyWrite, Request};
-use std::time::{Duration, SystemTime};
+use std::time::{Duration, Instant, SystemTime};
 use std::ffi::OsStr;
+use std::fs;
+use std::io::{Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
 
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileInfo {
     pub permissions: u16,       
-    pub links: u32,                 // always 1
+    pub links: u32,                 // hard links of a file, 2 + subdirectories for a directory
     pub owner: String,              // owner username
-    pub group: String,              // group (always users)
+    pub group: String,              // group name ("users" if the item has no group)
     pub size: u64,                  // dimension in bytes
     pub modified: String,           // last modifiied date
     pub name: String,               // name of the file/directory
     pub is_directory: bool,         // flag to identify wether it is a directory or not
+    #[serde(default)]
+    pub accessed: String,           // last access date
+    #[serde(default)]
+    pub changed: String,            // last change of content or metadata
+    #[serde(default)]
+    pub created: String,            // creation date
+    #[serde(default)]
+    pub is_symlink: bool,           // flag to identify symbolic links
+    #[serde(default)]
+    pub file_id: Option<u64>,       // id of the item on the server, the inode is file_id + 1 (None for virtual dirs)
 }
 
+impl FileInfo {
+    fn kind(&self) -> FileType {
+        if self.is_symlink {
+            FileType::Symlink
+        } else if self.is_directory {
+            FileType::Directory
+        } else {
+            FileType::RegularFile
+        }
+    }
+
+    // atime, mtime, ctime and crtime; the modification date is used for the ones the server doesn't send
+    fn times(&self) -> (SystemTime, SystemTime, SystemTime, SystemTime) {
+        let mtime = parse_time(&self.modified);
+        let or_mtime = |s: &str| if s.is_empty() { mtime } else { parse_time(s) };
+        (or_mtime(&self.accessed), mtime, or_mtime(&self.changed), or_mtime(&self.created))
+    }
+}
+
+// errno corresponding to an error answer of the server
+fn status_to_errno(status: reqwest::StatusCode) -> i32 {
+    match status {
+        reqwest::StatusCode::NOT_FOUND => ENOENT,
+        reqwest::StatusCode::FORBIDDEN | reqwest::StatusCode::UNAUTHORIZED => libc::EACCES,
+        reqwest::StatusCode::CONFLICT => libc::EEXIST,
+        reqwest::StatusCode::BAD_REQUEST => libc::EINVAL,
+        reqwest::StatusCode::INSUFFICIENT_STORAGE => libc::EDQUOT,
+        _ => EIO,
+    }
+}
+
+// the server sends RFC3339 timestamps, the sub-second part is kept (make compares mtimes)
 fn parse_time(s: &str) -> SystemTime {
     match DateTime::parse_from_rfc3339(s) {
-        Ok(dt) => SystemTime::UNIX_EPOCH + std::time::Duration::from_secs(dt.timestamp() as u64),
+        Ok(dt) => SystemTime::from(dt),
         Err(_) => SystemTime::now(),
     }
 }
 
+// How long the attributes and the directory listings are reused before asking the server again.
+// With Ttl an entry expires after the given time; with Lru at most that many entries are kept
+// and they stay valid until evicted or changed by this client, so changes made by others are
+// seen only after an eviction
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CachePolicy {
+    None,
+    Ttl(Duration),
+    Lru(usize),
+}
+
+impl CachePolicy {
+    // the same validity is given to the kernel for its own entry/attr cache
+    fn kernel_ttl(&self) -> Duration {
+        match self {
+            CachePolicy::None => Duration::ZERO,
+            CachePolicy::Ttl(ttl) => *ttl,
+            CachePolicy::Lru(_) => Duration::new(1, 0),
+        }
+    }
+}
+
+impl std::str::FromStr for CachePolicy {
+    type Err = String;
+
+    // "none", "ttl:<seconds>" or "lru:<entries>"
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (kind, value) = s.split_once(':').unwrap_or((s, ""));
+        match kind {
+            "none" => Ok(CachePolicy::None),
+            "ttl" => value.parse::<f64>().ok()
+                .filter(|secs| *secs >= 0.0)
+                .map(|secs| CachePolicy::Ttl(Duration::from_secs_f64(secs)))
+                .ok_or(format!("invalid cache ttl '{}', it must be a number of seconds", value)),
+            "lru" => value.parse::<usize>().ok()
+                .filter(|entries| *entries > 0)
+                .map(CachePolicy::Lru)
+                .ok_or(format!("invalid cache size '{}', it must be a positive number of entries", value)),
+            _ => Err(format!("unknown cache policy '{}', use none, ttl:<seconds> or lru:<entries>", s)),
+        }
+    }
+}
+
+struct CacheEntry<T> {
+    value: T,
+    inserted: Instant,
+    last_used: u64,
+}
+
+// attributes by path and listings by directory path, kept according to the policy
+struct MetadataCache {
+    policy: CachePolicy,
+    attrs: HashMap<String, CacheEntry<FileInfo>>,
+    dirs: HashMap<String, CacheEntry<Vec<FileInfo>>>,
+    tick: u64,      // logical clock for the least recently used entry
+}
+
+impl MetadataCache {
+    fn new(policy: CachePolicy) -> Self {
+        Self { policy, attrs: HashMap::new(), dirs: HashMap::new(), tick: 0 }
+    }
+
+    fn lookup<T: Clone>(map: &mut HashMap<String, CacheEntry<T>>, policy: CachePolicy, tick: u64, key: &str) -> Option<T> {
+        let valid = match (policy, map.get(key)) {
+            (_, None) | (CachePolicy::None, _) => false,
+            (CachePolicy::Ttl(ttl), Some(entry)) => entry.inserted.elapsed() < ttl,
+            (CachePolicy::Lru(_), Some(_)) => true,
+        };
+        if !valid {
+            map.remove(key);
+            return None;
+        }
+        let entry = map.get_mut(key)?;
+        entry.last_used = tick;
+        Some(entry.value.clone())
+    }
+
+    fn store<T>(map: &mut HashMap<String, CacheEntry<T>>, policy: CachePolicy, tick: u64, key: &str, value: T) {
+        match policy {
+            CachePolicy::None => return,
+            // the expired entries are dropped now and then, not to grow forever
+            CachePolicy::Ttl(ttl) if map.len() >= 4096 => map.retain(|_, e| e.inserted.elapsed() < ttl),
+            CachePolicy::Lru(capacity) if map.len() >= capacity && !map.contains_key(key) => {
+                let oldest = map.iter().min_by_key(|(_, e)| e.last_used).map(|(k, _)| k.clone());
+                if let Some(oldest) = oldest {
+                    map.remove(&oldest);
+                }
+            },
+            _ => {},
+        }
+        map.insert(key.to_string(), CacheEntry { value, inserted: Instant::now(), last_used: tick });
+    }
+
+    fn get_attr(&mut self, path: &str) -> Option<FileInfo> {
+        self.tick += 1;
+        Self::lookup(&mut self.attrs, self.policy, self.tick, path)
+    }
+
+    fn put_attr(&mut self, path: &str, info: FileInfo) {
+        self.tick += 1;
+        Self::store(&mut self.attrs, self.policy, self.tick, path, info);
+    }
+
+    fn get_dir(&mut self, path: &str) -> Option<Vec<FileInfo>> {
+        self.tick += 1;
+        Self::lookup(&mut self.dirs, self.policy, self.tick, path)
+    }
+
+    // a listing carries the attributes of the children too, so "ls -l" needs a single request
+    fn put_dir(&mut self, path: &str, files: Vec<FileInfo>) {
+        for info in &files {
+            self.put_attr(&format!("{}/{}", path, info.name), info.clone());
+        }
+        self.tick += 1;
+        Self::store(&mut self.dirs, self.policy, self.tick, path, files);
+    }
+
+    // an item was created, changed or removed: its attributes and the listing of its directory are stale
+    fn invalidate(&mut self, path: &str) {
+        self.attrs.remove(path);
+        self.dirs.remove(path);
+        let parent = path.rsplit_once('/').map(|(parent, _)| parent).unwrap_or("");
+        self.dirs.remove(parent);
+    }
+
+    // same as invalidate, also for everything below a directory (rename, rmdir)
+    fn invalidate_tree(&mut self, path: &str) {
+        self.invalidate(path);
+        let prefix = format!("{}/", path);
+        self.attrs.retain(|p, _| !p.starts_with(&prefix));
+        self.dirs.retain(|p, _| !p.starts_with(&prefix));
+    }
+}
+
+// file with the list of the cached contents, in the cache directory
+const CONTENT_INDEX_FILE: &str = "index.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedContent {
+    file: String,       // name of the copy in the cache directory
+    etag: String,       // ETag sent by the server with the content
+    size: u64,
+    last_used: u64,
+    #[serde(skip)]
+    fresh: bool,        // revalidated since the last open, not saved: every mount revalidates
+}
+
+// Copies of the files read through the mount, kept on the local disk between mounts.
+// A copy is used only after checking with the server (If-None-Match) that it is still the
+// current content, once per open; the least recently used copies are dropped above max_bytes
+pub struct ContentCache {
+    dir: PathBuf,
+    max_bytes: u64,
+    entries: HashMap<String, CachedContent>,
+    used_bytes: u64,
+    tick: u64,
+}
+
+impl ContentCache {
+    pub fn open(dir: &Path, max_bytes: u64) -> std::io::Result<Self> {
+        fs::create_dir_all(dir)?;
+        let mut entries: HashMap<String, CachedContent> = fs::read(dir.join(CONTENT_INDEX_FILE)).ok()
+            .and_then(|index| serde_json::from_slice(&index).ok())
+            .unwrap_or_default();
+
+        // copies lost or left without an entry (e.g. a crash while downloading) are dropped
+        entries.retain(|_, e| fs::metadata(dir.join(&e.file)).map(|m| m.len() == e.size).unwrap_or(false));
+        let known: std::collections::HashSet<&String> = entries.values().map(|e| &e.file).collect();
+        for item in fs::read_dir(dir)?.flatten() {
+            let name = item.file_name().to_string_lossy().to_string();
+            if name != CONTENT_INDEX_FILE && !known.contains(&name) {
+                let _ = fs::remove_file(item.path());
+            }
+        }
+
+        let mut cache = Self {
+            dir: dir.to_path_buf(),
+            max_bytes,
+            used_bytes: entries.values().map(|e| e.size).sum(),
+            tick: entries.values().map(|e| e.last_used).max().unwrap_or(0),
+            entries,
+        };
+        cache.evict(0);
+        cache.save();
+        Ok(cache)
+    }
+
+    fn save(&self) {
+        let index = serde_json::to_vec(&self.entries).unwrap_or_default();
+        let temp = self.dir.join(format!("{}.tmp", CONTENT_INDEX_FILE));
+        if fs::write(&temp, index).and_then(|_| fs::rename(&temp, self.dir.join(CONTENT_INDEX_FILE))).is_err() {
+            println!("salvataggio dell'indice della cache fallito");
+        }
+    }
+
+    fn file_name(path: &str) -> String {
+        use std::hash::{Hash, Hasher};
+        let mut hasher = std::collections::hash_map::DefaultHasher::new();
+        path.hash(&mut hasher);
+        format!("{:016x}", hasher.finish())
+    }
+
+    // drop the least recently used copies until `incoming` more bytes fit
+    fn evict(&mut self, incoming: u64) {
+        while self.used_bytes + incoming > self.max_bytes {
+            let Some(oldest) = self.entries.iter().min_by_key(|(_, e)| e.last_used).map(|(p, _)| p.clone()) else {
+                break;
+            };
+            self.remove(&oldest);
+        }
+    }
+
+    fn remove(&mut self, path: &str) {
+        if let Some(entry) = self.entries.remove(path) {
+            let _ = fs::remove_file(self.dir.join(&entry.file));
+            self.used_bytes -= entry.size;
+        }
+    }
+
+    // the file was opened again: the copy has to be revalidated before the next read
+    fn mark_stale(&mut self, path: &str) {
+        if let Some(entry) = self.entries.get_mut(path) {
+            entry.fresh = false;
+        }
+    }
+
+    // the file was changed through the mount
+    fn invalidate(&mut self, path: &str) {
+        if self.entries.contains_key(path) {
+            self.remove(path);
+            self.save();
+        }
+    }
+
+    // same as invalidate, for everything below a directory too
+    fn invalidate_tree(&mut self, path: &str) {
+        let prefix = format!("{}/", path);
+        let paths: Vec<String> = self.entries.keys()
+            .filter(|p| p.as_str() == path || p.starts_with(&prefix))
+            .cloned()
+            .collect();
+        if !paths.is_empty() {
+            paths.iter().for_each(|p| self.remove(p));
+            self.save();
+        }
+    }
+
+    // tag to send in If-None-Match, if there is a copy to revalidate
+    fn etag(&self, path: &str) -> Option<String> {
+        self.entries.get(path).map(|e| e.etag.clone())
+    }
+
+    fn is_fresh(&self, path: &str) -> bool {
+        self.entries.get(path).is_some_and(|e| e.fresh)
+    }
+
+    fn revalidated(&mut self, path: &str) {
+        if let Some(entry) = self.entries.get_mut(path) {
+            entry.fresh = true;
+        }
+    }
+
+    // where to download a new content, moved in place by store
+    fn temp_path(&self, path: &str) -> PathBuf {
+        self.dir.join(format!("{}.download", Self::file_name(path)))
+    }
+
+    fn store(&mut self, path: &str, downloaded: &Path, etag: String) {
+        self.remove(path);
+        let size = fs::metadata(downloaded).map(|m| m.len()).unwrap_or(0);
+        if size > self.max_bytes {
+            let _ = fs::remove_file(downloaded);
+            return;
+        }
+        self.evict(size);
+
+        let file = Self::file_name(path);
+        if fs::rename(downloaded, self.dir.join(&file)).is_err() {
+            let _ = fs::remove_file(downloaded);
+            return;
+        }
+        self.tick += 1;
+        self.entries.insert(path.to_string(), CachedContent { file, etag, size, last_used: self.tick, fresh: true });
+        self.used_bytes += size;
+        self.save();
+    }
+
+    fn read(&mut self, path: &str, offset: u64, size: u32) -> Option<Vec<u8>> {
+        self.tick += 1;
+        let entry = self.entries.get_mut(path)?;
+        entry.last_used = self.tick;
+        let mut file = fs::File::open(self.dir.join(&entry.file)).ok()?;
+        file.seek(SeekFrom::Start(offset)).ok()?;
+        let mut content = Vec::new();
+        file.take(size as u64).read_to_end(&mut content).ok()?;
+        Some(content)
+    }
+}
+
+// answer of a conditional GET /files for the content cache
+enum Revalidation {
+    NotModified,
+    Downloaded(String),     // new content, with its ETag
+    NoTag,                  // the server sent no ETag, the content can't be cached
+}
+
+// above this many buffered bytes a handle uploads them without waiting for the flush
+const MAX_DIRTY_BYTES: usize = 64 * 1024 * 1024;
+
+// size of a single PATCH, the server refuses bodies bigger than 2 MiB
+const MAX_UPLOAD_BYTES: usize = 1024 * 1024;
+
+// an open file: how it was opened and the data written and not yet sent to the server
+struct OpenFile {
+    ino: u64,
+    flags: i32,                     // flags of open/create (O_RDONLY, O_APPEND, ...)
+    append_at: Option<u64>,         // with O_APPEND the end of the file, where the next write goes
+    dirty: Vec<(u64, Vec<u8>)>,     // (offset, data) in write order, consecutive writes are merged
+    dirty_bytes: usize,
+    error: Option<i32>,             // failed upload not yet reported, returned by the next flush or fsync
+}
+
+impl OpenFile {
+    fn new(ino: u64, flags: i32) -> Self {
+        Self { ino, flags, append_at: None, dirty: Vec::new(), dirty_bytes: 0, error: None }
+    }
+
+    fn is_read_only(&self) -> bool {
+        self.flags & libc::O_ACCMODE == libc::O_RDONLY
+    }
+
+    fn add(&mut self, offset: u64, data: &[u8]) {
+        match self.dirty.last_mut() {
+            Some((start, buffer)) if *start + buffer.len() as u64 == offset => buffer.extend_from_slice(data),
+            _ => self.dirty.push((offset, data.to_vec())),
+        }
+        self.dirty_bytes += data.len();
+    }
+
+    // end of the buffered data, the file is at least this long
+    fn dirty_end(&self) -> Option<u64> {
+        self.dirty.iter().map(|(offset, data)| offset + data.len() as u64).max()
+    }
+
+    fn take_dirty(&mut self) -> Vec<(u64, Vec<u8>)> {
+        self.dirty_bytes = 0;
+        std::mem::take(&mut self.dirty)
+    }
+}
+
+// the token is renewed when it has less than this left
+const TOKEN_REFRESH_MARGIN: Duration = Duration::from_secs(60);
+
+// inodes of the items without an id on the server (the shared/ directories), far from the ids of the server
+const LOCAL_INO_BASE: u64 = 1 << 48;
+
+// answer of POST /auth/refresh
+#[derive(Debug, Deserialize)]
+struct RefreshResponse {
+    token: String,
+    refresh_token: String,
+    expires_in: u64,
+}
+
 pub struct RemoteFS {
     base_url: String,
     token: String,
+    refresh_token: Option<String>,
+    token_expires_at: Option<Instant>,
     inode_to_path: HashMap<u64, String>,
+    path_to_ino: HashMap<String, u64>,  // every known name, the hard links of a file have the same inode
     path_to_parent: HashMap<String, u64>,
     next_ino: u64,
     uid: u32,
     gid: u32,
+    cache: MetadataCache,
+    content: Option<ContentCache>,     // None if the content cache is disabled
+    handles: HashMap<u64, OpenFile>,
+    next_fh: u64,
+    write_back: bool,                   // writes buffered until flush, otherwise sent one by one
 }
 
 impl RemoteFS {
@@ -51,50 +468,459 @@ impl RemoteFS {
         Self {
             base_url,
             token,
+            refresh_token: None,
+            token_expires_at: None,
             inode_to_path: map,
+            path_to_ino: HashMap::from([("".to_string(), 1)]),
             path_to_parent: map_parent,
-            next_ino: 2,
+            next_ino: LOCAL_INO_BASE,
             uid,
-            gid
+            gid,
+            cache: MetadataCache::new(CachePolicy::Ttl(Duration::new(1, 0))),
+            content: None,
+            handles: HashMap::new(),
+            next_fh: 1,
+            write_back: true,
         }
     }
+
+    // every write is sent to the server at once, as soon as the kernel passes it
+    pub fn set_write_through(&mut self, write_through: bool) {
+        self.write_back = !write_through;
+    }
+
+    pub fn set_content_cache(&mut self, content: ContentCache) {
+        self.content = Some(content);
+    }
+
+    // chosen at mount time, the cache starts empty
+    pub fn set_cache_policy(&mut self, policy: CachePolicy) {
+        self.cache = MetadataCache::new(policy);
+    }
     
-    fn register_path(&mut self, path: &str) -> u64 {
-        if let Some((&ino, _)) = self.inode_to_path.iter().find(|(_, p)| p.as_str() == path) {
-            return ino;
+    // with a refresh token the access token is renewed on its own before it expires
+    pub fn set_refresh_token(&mut self, refresh_token: String, expires_in: u64) {
+        self.refresh_token = Some(refresh_token);
+        self.token_expires_at = Some(Instant::now() + Duration::from_secs(expires_in));
+    }
+
+    // token for the next request, renewed if it is about to expire
+    fn token(&mut self) -> String {
+        let expiring = self.token_expires_at
+            .map(|at| at.saturating_duration_since(Instant::now()) < TOKEN_REFRESH_MARGIN)
+            .unwrap_or(false);
+        if let (true, Some(refresh_token)) = (expiring, self.refresh_token.clone()) {
+            let client = Client::new();
+            let base_url = self.base_url.clone();
+            let refreshed: Option<RefreshResponse> = task::block_in_place(|| {
+                let rt = tokio::runtime::Handle::current();
+                rt.block_on(async {
+                    let resp = client
+                        .post(format!("{}/auth/refresh", base_url))
+                        .json(&serde_json::json!({ "refresh_token": refresh_token }))
+                        .send()
+                        .await;
+                    match resp {
+                        Ok(r) if r.status().is_success() => r.json::<RefreshResponse>().await.ok(),
+                        _ => None,
+                    }
+                })
+            });
+
+            match refreshed {
+                Some(r) => {
+                    println!("token rinnovato");
+                    self.token = r.token;
+                    self.set_refresh_token(r.refresh_token, r.expires_in);
+                }
+                // the old token is used until it expires, then the requests fail with EACCES
+                None => println!("rinnovo del token fallito"),
+            }
+        }
+        self.token.clone()
+    }
+
+    // the inode of an item is its id on the server + 1 (1 is the root of the mount), so lookup and
+    // readdir agree and it follows the item when it is renamed, also by other clients
+    fn register_path(&mut self, path: &str, info: &FileInfo) -> u64 {
+        let known = self.exist_path(path);
+        let ino = match (info.file_id, known) {
+            (Some(id), _) => id + 1,
+            (None, Some(ino)) => ino,
+            (None, None) => {
+                let ino = self.next_ino;
+                self.next_ino += 1;
+                ino
+            }
+        };
+
+        // the path was another item, deleted or replaced from outside the mount
+        if let Some(old_ino) = known.filter(|&old_ino| old_ino != ino) {
+            self.path_to_ino.remove(path);
+            if self.inode_to_path.get(&old_ino).is_some_and(|p| p == path) {
+                self.release_ino(old_ino);
+            }
         }
-        let ino = self.next_ino;
-        self.next_ino += 1;
-        self.inode_to_path.insert(ino, path.to_string());
 
         // registra il parent
-        if let Some(parent_path) = path.rsplit_once('/') {
-            let parent = parent_path.0;
-            if let Some((&parent_ino, _)) = self.inode_to_path.iter().find(|(_, p)| p.as_str() == parent) {
-                self.path_to_parent.insert(path.to_string(), parent_ino);
-             }
+        let parent_ino = path.rsplit_once('/').and_then(|(parent, _)| self.exist_path(parent));
+        match self.inode_to_path.get(&ino).cloned() {
+            // moved from outside the mount: the inode follows it. Another name of a hard link is only added
+            Some(old_path) if old_path != path && (info.is_directory || info.links <= 1) => {
+                self.rename_path(&old_path, path, parent_ino.unwrap_or(1));
+            }
+            Some(_) => {},
+            None => { self.inode_to_path.insert(ino, path.to_string()); },
+        }
+        self.path_to_ino.insert(path.to_string(), ino);
+        if let Some(parent_ino) = parent_ino {
+            self.path_to_parent.insert(path.to_string(), parent_ino);
         }
         ino
     }
 
+    // a deleted item and everything below it lose their names
+    fn forget_path(&mut self, path: &str) {
+        let prefix = format!("{}/", path);
+        let gone = |p: &str| p == path || p.starts_with(&prefix);
+        self.path_to_ino.retain(|p, _| !gone(p));
+        self.path_to_parent.retain(|p, _| !gone(p));
+
+        let orphans: Vec<u64> = self.inode_to_path.iter()
+            .filter(|(_, p)| gone(p))
+            .map(|(&ino, _)| ino)
+            .collect();
+        for ino in orphans {
+            self.release_ino(ino);
+        }
+    }
+
+    // the name of an inode is gone: a hard link keeps the inode with another of its names,
+    // a file still open keeps the old one until it is released, otherwise the inode is dropped
+    fn release_ino(&mut self, ino: u64) {
+        let other_name = self.path_to_ino.iter().find(|(_, i)| **i == ino).map(|(p, _)| p.clone());
+        match other_name {
+            Some(path) => { self.inode_to_path.insert(ino, path); },
+            None if self.handles.values().any(|h| h.ino == ino) => {},
+            None => { self.inode_to_path.remove(&ino); },
+        }
+    }
+
     fn get_path(&self, ino: u64) -> Option<String> {
         self.inode_to_path.get(&ino).cloned()
     }
 
-    fn exist_path(&mut self, path: &str)-> Option<u64>{
-        if let Some((&ino, _)) = self.inode_to_path.iter().find(|(_, p)| p.as_str() == path) {
-            Some(ino)
-        }else{
-            None
+    fn exist_path(&self, path: &str) -> Option<u64> {
+        self.path_to_ino.get(path).copied()
+    }
+
+    // after a rename the item and everything below it have a new path, the inodes stay the same
+    fn rename_path(&mut self, old_path: &str, new_path: &str, new_parent: u64) {
+        let old_prefix = format!("{}/", old_path);
+        for path in self.inode_to_path.values_mut() {
+            if path == old_path {
+                *path = new_path.to_string();
+            } else if let Some(rest) = path.strip_prefix(&old_prefix) {
+                *path = format!("{}/{}", new_path, rest);
+            }
+        }
+
+        let moved: Vec<String> = self.path_to_ino.keys()
+            .filter(|p| p.as_str() == old_path || p.starts_with(&old_prefix))
+            .cloned()
+            .collect();
+        for path in moved {
+            if let Some(ino) = self.path_to_ino.remove(&path) {
+                self.path_to_ino.insert(format!("{}{}", new_path, &path[old_path.len()..]), ino);
+            }
+        }
+
+        let moved: Vec<(String, u64)> = self.path_to_parent.iter()
+            .filter(|(p, _)| p.as_str() == old_path || p.starts_with(&old_prefix))
+            .map(|(p, ino)| (p.clone(), *ino))
+            .collect();
+        for (path, parent_ino) in moved {
+            self.path_to_parent.remove(&path);
+            let renamed = format!("{}{}", new_path, &path[old_path.len()..]);
+            let parent_ino = if path == old_path { new_parent } else { parent_ino };
+            self.path_to_parent.insert(renamed, parent_ino);
+        }
+    }
+
+    // attributes of an item, from the cache if still valid, otherwise from GET /lookup
+    fn lookup_info(&mut self, path: &str) -> Option<FileInfo> {
+        if let Some(info) = self.cache.get_attr(path) {
+            return Some(info);
+        }
+
+        let client = Client::new();
+        let token = self.token();
+        let base_url = self.base_url.clone();
+        let info: Option<FileInfo> = task::block_in_place(|| {
+            let rt = tokio::runtime::Handle::current();
+            rt.block_on(async {
+                let resp = client
+                    .get(format!("{}/lookup/{}", base_url, path)) // path già con /
+                    .bearer_auth(token)
+                    .send()
+                    .await;
+
+                match resp {
+                    Ok(r) if r.status().is_success() => r.json::<FileInfo>().await.ok(),
+                    _ => None,
+                }
+            })
+        });
+
+        if let Some(ref info) = info {
+            self.cache.put_attr(path, info.clone());
+        }
+        info
+    }
+
+    // content of a directory, from the cache if still valid, otherwise from GET /list
+    fn list_dir(&mut self, path: &str) -> Vec<FileInfo> {
+        if let Some(files) = self.cache.get_dir(path) {
+            return files;
+        }
+
+        let client = Client::new();
+        let token = self.token();
+        let base_url = self.base_url.clone();
+        let files: Option<Vec<FileInfo>> = task::block_in_place(|| {
+            let rt = tokio::runtime::Handle::current();
+            rt.block_on(async {
+                let resp = client
+                    .get(format!("{}/list/{}", base_url, path)) // path già con /
+                    .bearer_auth(token)
+                    .send()
+                    .await;
+
+                match resp {
+                    Ok(r) if r.status().is_success() => r.json::<Vec<FileInfo>>().await.ok(),
+                    _ => None,
+                }
+            })
+        });
+
+        match files {
+            Some(files) => {
+                self.cache.put_dir(path, files.clone());
+                files
+            }
+            None => Vec::new(),
+        }
+    }
+
+    // read through the content cache: the copy is revalidated once per open and downloaded whole if
+    // missing or changed. None if the file can't be cached, then only the asked range is fetched
+    fn read_cached(&mut self, path: &str, offset: u64, size: u32) -> Option<Result<Vec<u8>, i32>> {
+        let content = self.content.as_ref()?;
+        if !content.is_fresh(path) {
+            let (etag, temp, max_bytes) = (content.etag(path), content.temp_path(path), content.max_bytes);
+            // a file bigger than the whole cache is never downloaded
+            if self.lookup_info(path)?.size > max_bytes {
+                return None;
+            }
+
+            let client = Client::new();
+            let token = self.token();
+            let base_url = self.base_url.clone();
+            let result: Result<Revalidation, i32> = task::block_in_place(|| {
+                let rt = tokio::runtime::Handle::current();
+                rt.block_on(async {
+                    let mut request = client
+                        .get(format!("{}/files/{}", base_url, path))
+                        .bearer_auth(token);
+                    if let Some(etag) = etag {
+                        request = request.header(reqwest::header::IF_NONE_MATCH, etag);
+                    }
+
+                    let mut r = match request.send().await {
+                        Ok(r) if r.status() == reqwest::StatusCode::NOT_MODIFIED => return Ok(Revalidation::NotModified),
+                        Ok(r) if r.status().is_success() => r,
+                        Ok(r) => return Err(status_to_errno(r.status())),
+                        Err(_) => return Err(EIO),
+                    };
+                    let Some(etag) = r.headers().get(reqwest::header::ETAG).and_then(|t| t.to_str().ok()).map(String::from) else {
+                        return Ok(Revalidation::NoTag);
+                    };
+                    let mut file = fs::File::create(&temp).map_err(|_| EIO)?;
+                    while let Some(chunk) = r.chunk().await.map_err(|_| EIO)? {
+                        file.write_all(&chunk).map_err(|_| EIO)?;
+                    }
+                    Ok(Revalidation::Downloaded(etag))
+                })
+            });
+
+            let content = self.content.as_mut()?;
+            match result {
+                Ok(Revalidation::NotModified) => content.revalidated(path),
+                Ok(Revalidation::Downloaded(etag)) => content.store(path, &temp, etag),
+                Ok(Revalidation::NoTag) => return None,
+                Err(errno) => {
+                    let _ = fs::remove_file(&temp);
+                    content.invalidate(path);
+                    return Some(Err(errno));
+                }
+            }
         }
+
+        self.content.as_mut()?.read(path, offset, size).map(Ok)
     }
 
-    fn join_path(parent: &str, name: &str) -> String {
-    if parent == "/" {
-        format!("/{}", name)
-    } else {
-        format!("{}/{}", parent, name)
+    fn open_handle(&mut self, ino: u64, flags: i32) -> u64 {
+        let mut handle = OpenFile::new(ino, flags);
+        // appends go after the current end on the server, which may be newer than the one of the kernel
+        if flags & libc::O_APPEND != 0 {
+            let _ = self.flush_ino(ino);
+            if let Some(path) = self.get_path(ino) {
+                self.cache.invalidate(&path);
+                handle.append_at = Some(self.lookup_info(&path).map(|info| info.size).unwrap_or(0));
+            }
+        }
+
+        let fh = self.next_fh;
+        self.next_fh += 1;
+        self.handles.insert(fh, handle);
+        fh
     }
+
+    fn truncate_remote(&mut self, path: &str, size: u64) -> Result<(), i32> {
+        let client = Client::new();
+        let token = self.token();
+        let base_url = self.base_url.clone();
+        let result = task::block_in_place(|| {
+            let rt = tokio::runtime::Handle::current();
+            rt.block_on(async {
+                let resp = client
+                    .post(format!("{}/truncate/{}?size={}", base_url, path, size))
+                    .bearer_auth(token)
+                    .send()
+                    .await;
+                match resp {
+                    Ok(r) if r.status().is_success() => Ok(()),
+                    Ok(r) if r.status() == reqwest::StatusCode::BAD_REQUEST => Err(libc::EISDIR),
+                    Ok(r) => Err(status_to_errno(r.status())),
+                    Err(_) => Err(EIO),
+                }
+            })
+        });
+
+        self.cache.invalidate(path);
+        if let Some(content) = self.content.as_mut() {
+            content.invalidate(path);
+        }
+        result
+    }
+
+    // send the given writes in order (a later one can overwrite an earlier one)
+    fn upload(&mut self, path: &str, extents: Vec<(u64, Vec<u8>)>) -> Result<(), i32> {
+        if extents.is_empty() {
+            return Ok(());
+        }
+        let client = Client::new();
+        let token = self.token();
+        let base_url = self.base_url.clone();
+
+        let result = task::block_in_place(|| {
+            let rt = tokio::runtime::Handle::current();
+            rt.block_on(async {
+                // an extent can be bigger than the body accepted by the server, it is sent in pieces
+                let pieces = extents.iter().flat_map(|(offset, data)| {
+                    data.chunks(MAX_UPLOAD_BYTES)
+                        .enumerate()
+                        .map(move |(i, piece)| (offset + (i * MAX_UPLOAD_BYTES) as u64, piece))
+                });
+                for (offset, piece) in pieces {
+                    // the chunk is written at its offset, the rest of the file is kept
+                    let resp = client
+                        .patch(format!("{}/files/{}", base_url, path))
+                        .query(&[("offset", offset)])
+                        .bearer_auth(&token)
+                        .body(piece.to_vec())
+                        .send()
+                        .await;
+
+                    match resp {
+                        Ok(r) if r.status().is_success() => {},
+                        Ok(r) => return Err(status_to_errno(r.status())),
+                        Err(_) => return Err(EIO),
+                    }
+                }
+                Ok(())
+            })
+        });
+
+        // even a failed upload may have changed part of the file
+        self.cache.invalidate(path);
+        if let Some(content) = self.content.as_mut() {
+            content.invalidate(path);
+        }
+        result
+    }
+
+    // upload what a handle has buffered; if it fails the data is dropped
+    // and the error is also kept to be returned by the next flush or fsync
+    fn flush_handle(&mut self, fh: u64) -> Result<(), i32> {
+        let Some(handle) = self.handles.get_mut(&fh) else {
+            return Ok(());
+        };
+        let (ino, extents) = (handle.ino, handle.take_dirty());
+        let Some(path) = self.get_path(ino) else {
+            return Ok(());
+        };
+        let result = self.upload(&path, extents);
+        if let (Err(errno), Some(handle)) = (result, self.handles.get_mut(&fh)) {
+            handle.error.get_or_insert(errno);
+        }
+        result
+    }
+
+    // the server has to see the buffered writes before reading, truncating or renaming the file
+    fn flush_ino(&mut self, ino: u64) -> Result<(), i32> {
+        let handles: Vec<u64> = self.handles.iter().filter(|(_, h)| h.ino == ino).map(|(fh, _)| *fh).collect();
+        let mut result = Ok(());
+        for fh in handles {
+            let flushed = self.flush_handle(fh);
+            result = result.and(flushed);
+        }
+        result
+    }
+
+    // flush and fsync: the result of this upload or of a previous one that failed
+    fn sync_handle(&mut self, fh: u64) -> Result<(), i32> {
+        let result = self.flush_handle(fh);
+        match self.handles.get_mut(&fh).and_then(|h| h.error.take()) {
+            Some(errno) => Err(errno),
+            None => result,
+        }
+    }
+
+    fn file_attr(&self, ino: u64, obj: &FileInfo) -> FileAttr {
+        let (atime, mtime, ctime, crtime) = obj.times();
+        // the writes still buffered make the file longer than the server knows
+        let size = self.handles.values()
+            .filter(|h| h.ino == ino)
+            .filter_map(|h| h.dirty_end())
+            .fold(obj.size, u64::max);
+        FileAttr {
+            ino,
+            size,
+            blocks: (size / 512).max(1),
+            atime,
+            mtime,
+            ctime,
+            crtime,
+            kind: obj.kind(),
+            perm: obj.permissions,
+            nlink: obj.links,
+            uid: self.uid,
+            gid: self.gid,
+            rdev: 0,
+            flags: 0,
+            blksize: 512,
+        }
     }
     
 }
@@ -127,10 +953,10 @@ impl Filesystem for RemoteFS {
 
         // Chiamata remota al server (esempio)
         let client = Client::new();
-        let token = self.token.clone();
+        let token = self.token();
         let base_url = self.base_url.clone();
 
-        let success = task::block_in_place(|| {
+        let result = task::block_in_place(|| {
             let rt = tokio::runtime::Handle::current();
             rt.block_on(async {
                 let resp = client
@@ -138,39 +964,27 @@ impl Filesystem for RemoteFS {
                     .bearer_auth(token)
                     .send()
                     .await;
-            resp.map(|r| r.status().is_success()).unwrap_or(false)
+                match resp {
+                    Ok(r) if r.status().is_success() => Ok(()),
+                    Ok(r) => Err(status_to_errno(r.status())),
+                    Err(_) => Err(EIO),
+                }
             })
         });
 
-        if !success {
-            reply.error(EIO); // errore generico
+        if let Err(errno) = result {
+            reply.error(errno);
             return;
         }
+        self.cache.invalidate(&full_path);
 
-        // Se la creazione remota è andata bene, aggiorna la mappa inode↔path
-        let ino=self.register_path(&full_path);
-
-        // Costruisci gli attributi fittizi per la risposta
-        let ts = SystemTime::now();
-        let attr = FileAttr {
-            ino,
-            size: 0,
-            blocks: 0,
-            atime: ts,
-            mtime: ts,
-            ctime: ts,
-            crtime: ts,
-            kind: FileType::Directory,
-            perm: 0o755,
-            nlink: 2,
-            uid: self.uid,
-            gid: self.gid,
-            rdev: 0,
-            flags: 0,
-            blksize: 512,
-        };
-
-        reply.entry(&Duration::new(1, 0), &attr, 0);
+        // Se la creazione remota è andata bene, aggiorna la mappa inode↔path
+        let Some(info) = self.lookup_info(&full_path) else {
+            reply.error(EIO);
+            return;
+        };
+        let ino = self.register_path(&full_path, &info);
+        reply.entry(&self.cache.policy.kernel_ttl(), &self.file_attr(ino, &info), 0);
     }
 
 
@@ -185,12 +999,37 @@ impl Filesystem for RemoteFS {
         _lock_owner: Option<u64>,
         reply: ReplyData,
     ) {
-        let path = self.get_path(ino).unwrap();
+        // the inode of a deleted item can still be in the kernel: it has to look up the name again
+        let Some(path) = self.get_path(ino) else {
+            reply.error(libc::ESTALE);
+            return;
+        };
         println!("execute read {}", path);
         let client = Client::new();
-        let token = self.token.clone();
+        let token = self.token();
         let base_url = self.base_url.clone();
 
+        if size == 0 {
+            reply.data(&[]);
+            return;
+        }
+
+        if let Err(errno) = self.flush_ino(ino) {
+            reply.error(errno);
+            return;
+        }
+
+        if let Some(result) = self.read_cached(&path, offset as u64, size) {
+            match result {
+                Ok(content) => reply.data(&content),
+                Err(errno) => reply.error(errno),
+            }
+            return;
+        }
+
+        // only the bytes asked by the kernel
+        let range = format!("bytes={}-{}", offset, offset as u64 + size as u64 - 1);
+
         task::block_in_place(|| {
             
             let rt = tokio::runtime::Handle::current();
@@ -198,16 +1037,24 @@ impl Filesystem for RemoteFS {
                 let resp = client
                     .get(format!("{}/files/{}", base_url, path))
                     .bearer_auth(token)
+                    .header(reqwest::header::RANGE, range)
                     .send()
                     .await;
 
                 match resp {
+                    Ok(r) if r.status() == reqwest::StatusCode::PARTIAL_CONTENT => {
+                        let content = r.bytes().await.unwrap_or_default();
+                        reply.data(&content);
+                    }
                     Ok(r) if r.status().is_success() => {
+                        // the server ignored the range and sent the whole file
                         let content = r.bytes().await.unwrap_or_default();
-                        let start = offset as usize;
-                        let end = (offset as usize + size as usize).min(content.len());
+                        let start = (offset as usize).min(content.len());
+                        let end = (start + size as usize).min(content.len());
                         reply.data(&content[start..end]);
                     }
+                    // offset past the end of the file
+                    Ok(r) if r.status() == reqwest::StatusCode::RANGE_NOT_SATISFIABLE => reply.data(&[]),
                     _ => reply.error(ENOENT),
                 }
             });
@@ -216,10 +1063,17 @@ impl Filesystem for RemoteFS {
 
     fn getattr(&mut self, _: &Request, ino: u64, _: Option<u64>, reply: ReplyAttr) {
         
-        let path= self.get_path(ino).unwrap();
+        let Some(path) = self.get_path(ino) else {
+            reply.error(libc::ESTALE);
+            return;
+        };
         println!("getattr(ino={}, path={})", ino, path);
+        let ttl = self.cache.policy.kernel_ttl();
 
         if ino==1{
+            // the root has no METADATA row, its link count comes from the subdirectories
+            let subdirs = self.list_dir("").iter().filter(|f| f.is_directory).count() as u32;
+
             let ts = SystemTime::now();
             let attr = FileAttr {
                 ino,
@@ -231,452 +1085,707 @@ impl Filesystem for RemoteFS {
                 crtime: ts,
                 kind: FileType::Directory,
                 perm:  0o755,
-                nlink: 1,
+                nlink: 2 + subdirs,
                 uid: self.uid,
                 gid: self.gid,
                 rdev: 0,
                 flags: 0,
                 blksize: 512,
                 };
-                reply.attr(&Duration::new(1, 0), &attr);
+                reply.attr(&ttl, &attr);
 
         }else{
+            match self.lookup_info(&path) {
+                // the path is now another item, deleted and created again from outside the mount
+                Some(obj) if self.register_path(&path, &obj) != ino => reply.error(libc::ESTALE),
+                Some(obj) => reply.attr(&ttl, &self.file_attr(ino, &obj)),
+                None => reply.error(ENOENT),
+            }
+        }
+    }
+
+
+   
+
+    fn readdir(
+        &mut self, 
+        _req: &Request, 
+        ino: u64, _: u64, 
+        offset: i64, 
+        mut reply: 
+        ReplyDirectory) {
+
+        let path = match self.get_path(ino) {
+            Some(p) => p.clone(),
+            None => {
+                reply.error(ENOENT);
+                return;
+            }
+        };    
+        println!("readdir(ino={}, offset={}, path={})", ino, offset, path);
+
+    
+        let files = self.list_dir(&path);
+
+    let i = offset;
+
+        if i == 0 {
+            let current_ino = ino;
+            let _ = reply.add(current_ino, 1, FileType::Directory, ".");
+
+            // trova il parent
+            let parent_ino = if current_ino == 1 {
+                1 // root: parent == self
+            } else {
+                *self.path_to_parent.get(&path).unwrap_or(&1)
+            };
+
+            let _ = reply.add(parent_ino, 2, FileType::Directory, "..");
+        }
+
+
+        for (idx, item) in files.iter().enumerate().skip((i - 2) as usize) {
+            let name= item.name.clone();
+            let kind = item.kind();
+            let next_offset = (idx as i64) + 3; // offset successivo
+            let full_path = format!("{}/{}", path, name);
+            // same inode of lookup
+            let child_ino = self.register_path(&full_path, item);
+            
+            let _ =reply.add(child_ino, next_offset, kind, OsStr::new(&name));
+        }
+
+        reply.ok();
+    
+    }
+
+//controlla che file/dir esitano o meno
+    fn lookup(
+        &mut self,
+        _req: &Request<'_>,
+        parent: u64,
+        name: &OsStr,
+        reply: ReplyEntry,
+    ) {
+    
+        let Some(parent_path) = self.get_path(parent) else {
+            reply.error(libc::ESTALE);
+            return;
+        };
+        let path = if parent_path == "/" {
+            format!("/{}", name.to_str().unwrap())
+        } else {
+            format!("{}/{}", parent_path, name.to_str().unwrap())
+        };
+
+        let name_str = name.to_str().unwrap_or("");
+
+        // escludi le lookup spurie di comandi tipo "echo", "total", "drwxr-xr-x", numeri, ecc.
+        //é 
+        let is_spurious = name_str.chars().all(|c| c.is_numeric())
+            || name_str.starts_with("drwx")
+            || name_str.eq_ignore_ascii_case("total")
+            || name_str.eq_ignore_ascii_case("echo")
+            || name_str.eq_ignore_ascii_case("cat")
+            || name_str.eq_ignore_ascii_case("ls")
+            || name_str.eq_ignore_ascii_case("mkdir")
+            || name_str.eq_ignore_ascii_case("rmdir");
+
+        if is_spurious {
+            println!("Ignoro lookup spurio su {:?}", name_str);
+            reply.error(ENOENT);
+            return;
+        }
+
+        println!("lookup(parent={}, name={:?})", parent, name);
+
+        // a valid listing of the directory already tells that the name doesn't exist
+        let parent_key = path.rsplit_once('/').map(|(p, _)| p).unwrap_or("");
+        if self.cache.get_dir(parent_key).is_some_and(|files| !files.iter().any(|f| f.name == name_str)) {
+            reply.error(ENOENT);
+            return;
+        }
+
+        match self.lookup_info(&path) {
+            Some(obj) => {
+                let ino = self.register_path(&path, &obj);
+                reply.entry(&self.cache.policy.kernel_ttl(), &self.file_attr(ino, &obj), 0);
+            }
+            None => {
+                println!("lookup fallita per {}", path);
+                reply.error(ENOENT);
+            }
+        }
+    }
+
+    // the file is created empty on the server, in a single step that fails if the name is taken
+    fn create(
+        &mut self,
+        _req: &fuser::Request<'_>,
+        parent: u64,
+        name: &std::ffi::OsStr,
+        mode: u32,
+        umask: u32,
+        flags: i32,
+        reply: ReplyCreate,
+    ) {
+        println!("CREATE called for {:?} (flags=0o{:o})", name, flags);
+        let Some(parent_path) = self.get_path(parent) else {
+            reply.error(ENOENT);
+            return;
+        };
+        let path = format!("{}/{}", parent_path, name.to_str().unwrap_or(""));
+        let permissions = format!("{:03o}", mode & !umask & 0o777);
 
-        //API CALL
         let client = Client::new();
-        let token = self.token.clone();
+        let token = self.token();
         let base_url = self.base_url.clone();
-        task::block_in_place(|| {
+        let result: Result<(), i32> = task::block_in_place(|| {
             let rt = tokio::runtime::Handle::current();
             rt.block_on(async {
                 let resp = client
-                    .get(format!("{}/lookup/{}", base_url, path)) // path già con /
+                    .put(format!("{}/files/{}", base_url, path))
+                    .query(&[("exclusive", "true"), ("permissions", permissions.as_str())])
                     .bearer_auth(token)
                     .send()
                     .await;
 
                 match resp {
-                    Ok(r) if r.status().is_success() => {
-                        println!("risposta corretta");
-                        match r.json::<FileInfo>().await {
-                            Ok(obj) => {
-                                println!("json {:?}", obj);
-
-                                let kind = if obj.is_directory {
-                                    FileType::Directory
-                                } else {
-                                   FileType::RegularFile
-                                };
-
-                                let ino = self.register_path(&path);
-
-                                let ts = parse_time(&obj.modified);
-                                let attr = FileAttr {
-                                    ino,
-                                    size: obj.size,
-                                    blocks: (obj.size / 512).max(1),
-                                    atime: ts,
-                                    mtime: ts,
-                                    ctime: ts,
-                                    crtime: ts,
-                                    kind,
-                                    perm: obj.permissions,
-                                    nlink: obj.links,
-                                    uid: self.uid,
-                                    gid: self.gid,
-                                    rdev: 0,
-                                    flags: 0,
-                                    blksize: 512,
-                                };
-                                reply.attr(&Duration::new(1, 0), &attr);
-                            }
-                            Err(_) => reply.error(ENOENT),
-                        }
+                    Ok(r) if r.status().is_success() => Ok(()),
+                    Ok(r) => Err(status_to_errno(r.status())),
+                    Err(_) => Err(EIO),
                 }
-                _ => reply.error(ENOENT),
+            })
+        });
+
+        let result = match result {
+            // without O_EXCL a file created by someone else after the lookup of the kernel is just opened
+            Err(libc::EEXIST) if flags & libc::O_EXCL == 0 && flags & libc::O_TRUNC != 0 => self.truncate_remote(&path, 0),
+            Err(libc::EEXIST) if flags & libc::O_EXCL == 0 => Ok(()),
+            result => result,
+        };
+        if let Err(errno) = result {
+            reply.error(errno);
+            return;
+        }
+
+        self.cache.invalidate(&path);
+        let Some(info) = self.lookup_info(&path) else {
+            reply.error(ENOENT);
+            return;
+        };
+        let ino = self.register_path(&path, &info);
+        let fh = self.open_handle(ino, flags);
+        reply.created(&self.cache.policy.kernel_ttl(), &self.file_attr(ino, &info), 0, fh, 0);
+    }
+
+    fn open(&mut self, _req: &Request, ino: u64, flags: i32, reply: ReplyOpen) {
+        println!("open(ino={}, flags=0o{:o})", ino, flags);
+        let Some(path) = self.get_path(ino) else {
+            reply.error(ENOENT);
+            return;
+        };
+
+        // close-to-open: the cached content is checked with the server at the first read
+        if let Some(content) = self.content.as_mut() {
+            content.mark_stale(&path);
+        }
+
+        // usually the kernel truncates with a setattr before the open, this covers the other cases
+        if flags & libc::O_TRUNC != 0 && flags & libc::O_ACCMODE != libc::O_RDONLY {
+            let _ = self.flush_ino(ino);
+            if let Err(errno) = self.truncate_remote(&path, 0) {
+                reply.error(errno);
+                return;
+            }
+        }
+
+        let fh = self.open_handle(ino, flags);
+        reply.opened(fh, 0);
+    }
+
+    fn setattr(
+    &mut self,
+    req: &Request<'_>,
+    ino: u64,
+    mode: Option<u32>,
+    uid: Option<u32>,
+    gid: Option<u32>,
+    size: Option<u64>,
+    atime: Option<fuser::TimeOrNow>,
+    mtime: Option<fuser::TimeOrNow>,
+    _ctime: Option<SystemTime>,
+    _fh: Option<u64>,
+    _crtime: Option<SystemTime>,
+    _chgtime: Option<SystemTime>,
+    _bkuptime: Option<SystemTime>,
+    _flags: Option<u32>,
+    reply: ReplyAttr,
+    ) {
+        println!("setattr(ino={}, mode={:?}, uid={:?}, gid={:?}, size={:?}, atime={:?}, mtime={:?})", ino, mode, uid, gid, size, atime, mtime);
+
+        // every item of the mount belongs to the local user, other local uids/gids have no server user
+        if uid.is_some_and(|u| u != self.uid) || gid.is_some_and(|g| g != self.gid) {
+            reply.error(libc::EPERM);
+            return;
+        }
+
+        if ino == 1 {
+            // the root has no METADATA row, its permissions and times can't be changed
+            if mode.is_some() || atime.is_some() || mtime.is_some() {
+                reply.error(libc::EPERM);
+                return;
+            }
+            self.getattr(req, ino, None, reply);
+            return;
+        }
+
+        let Some(path) = self.get_path(ino) else {
+            reply.error(ENOENT);
+            return;
+        };
+
+        // the buffered writes come before the truncation
+        let flushed = if size.is_some() { self.flush_ino(ino) } else { Ok(()) };
+        if let Err(errno) = flushed {
+            reply.error(errno);
+            return;
+        }
+
+        // chmod and utimens ("touch -d", "cp -p") are a single update of the metadata
+        let mut changes = serde_json::Map::new();
+        if let Some(mode) = mode {
+            changes.insert("permissions".to_string(), format!("{:o}", mode & 0o777).into());
+        }
+        for (field, time) in [("atime", atime), ("mtime", mtime)] {
+            if let Some(time) = time {
+                let time = match time {
+                    fuser::TimeOrNow::SpecificTime(t) => t,
+                    fuser::TimeOrNow::Now => SystemTime::now(),
+                };
+                changes.insert(field.to_string(), DateTime::<chrono::Utc>::from(time).to_rfc3339().into());
             }
-                
+        }
+
+        let client = Client::new();
+        let token = self.token();
+        let base_url = self.base_url.clone();
+
+        // with new metadata the server answers with the updated attributes, otherwise they are looked up
+        let result: Result<FileInfo, i32> = task::block_in_place(|| {
+            let rt = tokio::runtime::Handle::current();
+            rt.block_on(async {
+                // truncation (e.g. "echo x > file" or "truncate -s")
+                if let Some(size) = size {
+                    let resp = client
+                        .post(format!("{}/truncate/{}?size={}", base_url, path, size))
+                        .bearer_auth(&token)
+                        .send()
+                        .await;
+                    match resp {
+                        Ok(r) if r.status().is_success() => {},
+                        Ok(r) if r.status() == reqwest::StatusCode::BAD_REQUEST => return Err(libc::EISDIR),
+                        Ok(r) => return Err(status_to_errno(r.status())),
+                        Err(_) => return Err(EIO),
+                    }
+                }
+
+                let resp = if changes.is_empty() {
+                    client
+                        .get(format!("{}/lookup/{}", base_url, path))
+                        .bearer_auth(&token)
+                        .send()
+                        .await
+                } else {
+                    client
+                        .post(format!("{}/metadata/{}", base_url, path))
+                        .bearer_auth(&token)
+                        .json(&changes)
+                        .send()
+                        .await
+                };
+
+                match resp {
+                    Ok(r) if r.status().is_success() => r.json::<FileInfo>().await.map_err(|_| EIO),
+                    Ok(r) if r.status() == reqwest::StatusCode::FORBIDDEN => Err(libc::EPERM),
+                    Ok(r) => Err(status_to_errno(r.status())),
+                    Err(_) => Err(EIO),
+                }
             })
         });
+
+        match result {
+            Ok(obj) => {
+                // the listing of the directory has the old size and times
+                self.cache.invalidate(&path);
+                if let (Some(content), Some(_)) = (self.content.as_mut(), size) {
+                    content.invalidate(&path);
+                }
+                let attr = self.file_attr(ino, &obj);
+                self.cache.put_attr(&path, obj);
+                reply.attr(&self.cache.policy.kernel_ttl(), &attr);
+            }
+            Err(errno) => {
+                self.cache.invalidate(&path);
+                reply.error(errno);
+            }
+        }
     }
-    
+
+    fn write(
+        &mut self,
+        _req: &Request<'_>,
+        ino: u64,
+        fh: u64,
+        offset: i64,
+        data: &[u8],
+        _: u32,
+        _flags: i32,
+        _lock_owner: Option<u64>,
+        reply: ReplyWrite,
+    ) {
+        
+        let Some(path) = self.get_path(ino) else {
+            reply.error(libc::ESTALE);
+            return;
+        };
+        println!("execute write {}", path);
+
+        let offset = match self.handles.get_mut(&fh) {
+            Some(handle) if handle.is_read_only() => {
+                reply.error(libc::EBADF);
+                return;
+            }
+            // O_APPEND: always at the end, whatever offset the kernel computed
+            Some(OpenFile { append_at: Some(end), .. }) => {
+                let at = *end;
+                *end += data.len() as u64;
+                at
+            }
+            _ => offset as u64,
+        };
+
+        // write-back: the data waits in the handle, a copy of many chunks becomes a single upload
+        let result = match self.handles.get_mut(&fh) {
+            Some(handle) if self.write_back => {
+                handle.add(offset, data);
+                let full = handle.dirty_bytes >= MAX_DIRTY_BYTES;
+                // the local copy of the content is older than the buffered data
+                if let Some(content) = self.content.as_mut() {
+                    content.invalidate(&path);
+                }
+                if full { self.flush_handle(fh) } else { Ok(()) }
+            }
+            // raw bytes, the content can be binary
+            _ => self.upload(&path, vec![(offset, data.to_vec())]),
+        };
+
+        match result {
+            Ok(()) => reply.written(data.len() as u32),
+            Err(errno) => reply.error(errno),
+        }
     }
 
+    // called at every close of the file descriptor: the error of the upload is the one of close()
+    fn flush(&mut self, _req: &Request, ino: u64, fh: u64, _lock_owner: u64, reply: ReplyEmpty) {
+        println!("flush(ino={}, fh={})", ino, fh);
+        match self.sync_handle(fh) {
+            Ok(()) => reply.ok(),
+            Err(errno) => reply.error(errno),
+        }
+    }
 
-   
+    fn fsync(&mut self, _req: &Request<'_>, ino: u64, fh: u64, datasync: bool, reply: ReplyEmpty) {
+        println!("fsync(ino={}, fh={}, datasync={})", ino, fh, datasync);
+        match self.sync_handle(fh) {
+            Ok(()) => reply.ok(),
+            Err(errno) => reply.error(errno),
+        }
+    }
 
-    fn readdir(
+    fn release(&mut self, _req: &Request, ino: u64, fh: u64, _flags: i32, _lock_owner: Option<u64>, _flush: bool, reply: ReplyEmpty) {
+        println!("release(ino={}, fh={})", ino, fh);
+        // normally the flush already sent everything
+        let result = self.sync_handle(fh);
+        self.handles.remove(&fh);
+        match result {
+            Ok(()) => reply.ok(),
+            Err(errno) => reply.error(errno),
+        }
+    }
+
+    
+    fn unlink(
         &mut self, 
         _req: &Request, 
-        ino: u64, _: u64, 
-        offset: i64, 
-        mut reply: 
-        ReplyDirectory) {
+        parent: u64, 
+        name: &OsStr, 
+        reply: ReplyEmpty) 
+    {
+        println!("unlink(parent={}, name={:?})", parent, name);
 
-        let path = match self.get_path(ino) {
-            Some(p) => p.clone(),
-            None => {
-                reply.error(ENOENT);
-                return;
-            }
-        };    
-        println!("readdir(ino={}, offset={}, path={})", ino, offset, path);
+        let Some(parent_path) = self.get_path(parent) else {
+        reply.error(ENOENT);
+        return;
+        };
+
+        let full_path = format!("{}/{}", parent_path, name.to_str().unwrap());
+        println!("Deleting {}", full_path);
 
-    
         let client = Client::new();
-        let token = self.token.clone();
+        let token = self.token();
         let base_url = self.base_url.clone();
 
-        let files: Vec<FileInfo> =task::block_in_place(|| {
+        let deleted = task::block_in_place(|| {
             let rt = tokio::runtime::Handle::current();
             rt.block_on(async {
                 let resp = client
-                    .get(format!("{}/list/{}", base_url, path)) // path già con /
+                    .delete(format!("{}/files/{}", base_url, full_path))
                     .bearer_auth(token)
                     .send()
                     .await;
 
-                    match resp {
-                    Ok(r) if r.status().is_success() =>{
-                        let v= r.json::<Vec<FileInfo>>().await;
-                        v.unwrap_or(Vec::new())                   
-                    },
-                    _ => Vec::new(),
-                    }
-                
+                matches!(resp, Ok(r) if r.status().is_success())
             })
         });
 
-    let i = offset;
-
-        if i == 0 {
-            let current_ino = ino;
-            let _ = reply.add(current_ino, 1, FileType::Directory, ".");
-
-            // trova il parent
-            let parent_ino = if current_ino == 1 {
-                1 // root: parent == self
-            } else {
-                let path = self.get_path(current_ino).unwrap();
-                *self.path_to_parent.get(&path).unwrap_or(&1)
-            };
-
-            let _ = reply.add(parent_ino, 2, FileType::Directory, "..");
-        }
-
-
-        for (idx, item) in files.iter().enumerate().skip((i - 2) as usize) {
-            let name= item.name.clone();
-            let kind;
-            if item.is_directory{
-                kind=FileType::Directory;
-            }else{
-                kind=FileType::RegularFile;
+        if deleted {
+            // the writes still buffered would create the file again
+            if let Some(ino) = self.exist_path(&full_path) {
+                self.handles.values_mut().filter(|h| h.ino == ino).for_each(|h| { h.take_dirty(); });
             }
-            let next_offset = (idx as i64) + 3; // offset successivo
-            let full_path = format!("{}/{}", path, name);
-            let _= self.register_path(&full_path);
-            
-            let _ =reply.add((idx as u64) + 2, next_offset, kind, OsStr::new(&name));
+            self.forget_path(&full_path);
+            self.cache.invalidate(&full_path);
+            if let Some(content) = self.content.as_mut() {
+                content.invalidate(&full_path);
+            }
+            reply.ok();
+        } else {
+            reply.error(EIO);
         }
-
-        reply.ok();
-    
+        
     }
 
-//controlla che file/dir esitano o meno
-    fn lookup(
+    fn rename(
         &mut self,
         _req: &Request<'_>,
         parent: u64,
         name: &OsStr,
-        reply: ReplyEntry,
+        newparent: u64,
+        newname: &OsStr,
+        flags: u32,
+        reply: ReplyEmpty,
     ) {
-    
-        let parent_path= self.get_path(parent).unwrap();
-        let path = if parent_path == "/" {
-            format!("/{}", name.to_str().unwrap())
-        } else {
-            format!("{}/{}", parent_path, name.to_str().unwrap())
-        };
+        println!("rename(parent={}, name={:?}, newparent={}, newname={:?})", parent, name, newparent, newname);
 
-        let name_str = name.to_str().unwrap_or("");
-
-        // escludi le lookup spurie di comandi tipo "echo", "total", "drwxr-xr-x", numeri, ecc.
-        //é 
-        let is_spurious = name_str.chars().all(|c| c.is_numeric())
-            || name_str.starts_with("drwx")
-            || name_str.eq_ignore_ascii_case("total")
-            || name_str.eq_ignore_ascii_case("echo")
-            || name_str.eq_ignore_ascii_case("cat")
-            || name_str.eq_ignore_ascii_case("ls")
-            || name_str.eq_ignore_ascii_case("mkdir")
-            || name_str.eq_ignore_ascii_case("rmdir");
+        // exchanging two items is not supported by the server
+        if flags & libc::RENAME_EXCHANGE != 0 {
+            reply.error(libc::EINVAL);
+            return;
+        }
 
-        if is_spurious {
-            println!("Ignoro lookup spurio su {:?}", name_str);
+        let (Some(parent_path), Some(new_parent_path)) = (self.get_path(parent), self.get_path(newparent)) else {
             reply.error(ENOENT);
             return;
-        }
+        };
+        let from = format!("{}/{}", parent_path, name.to_str().unwrap_or(""));
+        let to = format!("{}/{}", new_parent_path, newname.to_str().unwrap_or(""));
 
-        println!("lookup(parent={}, name={:?})", parent, name);
-      
-        //API CALL
         let client = Client::new();
-        let token = self.token.clone();
+        let token = self.token();
         let base_url = self.base_url.clone();
-        let res: Option<FileInfo>= task::block_in_place(|| {
+        let body = serde_json::json!({
+            "from": from,
+            "to": to,
+            "no_replace": flags & libc::RENAME_NOREPLACE != 0,
+        });
+
+        let result: Result<(), i32> = task::block_in_place(|| {
             let rt = tokio::runtime::Handle::current();
             rt.block_on(async {
                 let resp = client
-                    .get(format!("{}/lookup/{}", base_url, path)) // path già con /
+                    .post(format!("{}/rename", base_url))
                     .bearer_auth(token)
+                    .json(&body)
                     .send()
                     .await;
 
                 match resp {
-                    Ok(r) if r.status().is_success() => r.json::<FileInfo>().await.ok(),
-                    _ => None,
+                    Ok(r) if r.status().is_success() => Ok(()),
+                    Ok(r) => Err(status_to_errno(r.status())),
+                    Err(_) => Err(EIO),
                 }
             })
         });
 
-    match res {
-        Some(obj) => {
-            println!("json {:?}", obj);
-
-            let kind= if obj.is_directory {
-                FileType::Directory
-            } else {
-                FileType::RegularFile
-            };
-
-            let ino = self.register_path(&path);
-            let ts = parse_time(&obj.modified);
-            let attr = FileAttr {
-                ino,
-                size: obj.size,
-                blocks: (obj.size / 512).max(1),
-                atime: ts,
-                mtime: ts,
-                ctime: ts,
-                crtime: ts,
-                kind,
-                perm: obj.permissions,
-                nlink: obj.links,
-                uid: self.uid,
-                gid: self.gid,
-                rdev: 0,
-                flags: 0,
-                blksize: 512,
-            };
-
-            reply.entry(&Duration::new(10, 0), &attr, 0);
-        }
-        None => {
-            println!("lookup fallita per {}", path);
-            reply.error(ENOENT);
+        match result {
+            Ok(()) => {
+                // a replaced destination doesn't exist anymore
+                self.forget_path(&to);
+                self.rename_path(&from, &to, newparent);
+                self.cache.invalidate_tree(&from);
+                self.cache.invalidate_tree(&to);
+                if let Some(content) = self.content.as_mut() {
+                    content.invalidate_tree(&from);
+                    content.invalidate_tree(&to);
+                }
+                reply.ok();
+            }
+            Err(errno) => reply.error(errno),
         }
     }
 
-    
-    }
-
-    // create dummy: è necessaria per FUSe ma non chiama nessuna API
-    fn create(
+    fn symlink(
         &mut self,
-        _req: &fuser::Request<'_>,
+        _req: &Request<'_>,
         parent: u64,
-        name: &std::ffi::OsStr,
-        _mode: u32,
-        _size: u32,
-        _flags: i32,
-        reply: ReplyCreate,
+        link_name: &OsStr,
+        target: &std::path::Path,
+        reply: ReplyEntry,
     ) {
-        println!("CREATE called for {:?}", name);
-        let parent_path= self.get_path(parent).unwrap();
-        let real_path= parent_path.to_owned()+"/"+name.to_str().unwrap();
-        let ino= self.register_path(&real_path);
-        let ts=SystemTime::now();
-        let attr = FileAttr {
-            ino, 
-            size: 0,
-            blocks: 0,
-            atime: ts,
-            mtime: ts,
-            ctime: ts,
-            crtime: ts,
-            kind: FileType::RegularFile,
-            perm: 0o644,
-            nlink: 1,
-            uid: self.uid,
-            gid: self.gid,
-            rdev: 0,
-            flags: 0,
-            blksize: 512,
+        println!("symlink(parent={}, name={:?}, target={:?})", parent, link_name, target);
+
+        let Some(parent_path) = self.get_path(parent) else {
+            reply.error(ENOENT);
+            return;
         };
+        let full_path = format!("{}/{}", parent_path, link_name.to_str().unwrap_or(""));
+        let target = target.to_string_lossy().to_string();
 
-        // Non crea davvero nulla, ma fa contento il kernel
-        reply.created(&Duration::new(1, 0), &attr, 0, 0, 0);
-    }
+        let client = Client::new();
+        let token = self.token();
+        let base_url = self.base_url.clone();
+        let body = serde_json::json!({ "target": target });
 
-//DUMMY FUNCTION FOR FUSE
-    fn open(&mut self, _req: &Request, ino: u64, flags: i32, reply: ReplyOpen) {
-        println!("open(ino={})", ino);
-        if flags & libc::O_WRONLY != 0 || flags & libc::O_RDWR != 0 {
-        println!("--> opening file for write");
-       
-        } 
-        println!("open flags: 0o{:o}", flags);
+        let result: Result<(), i32> = task::block_in_place(|| {
+            let rt = tokio::runtime::Handle::current();
+            rt.block_on(async {
+                let resp = client
+                    .post(format!("{}/symlink/{}", base_url, full_path))
+                    .bearer_auth(token)
+                    .json(&body)
+                    .send()
+                    .await;
 
-    reply.opened(0, 0); // handle fittizio = 0, flags = 0
-    }
+                match resp {
+                    Ok(r) if r.status().is_success() => Ok(()),
+                    Ok(r) => Err(status_to_errno(r.status())),
+                    Err(_) => Err(EIO),
+                }
+            })
+        });
 
-    fn setattr(
-    &mut self,
-    _req: &Request<'_>,
-    ino: u64,
-    _mode: Option<u32>,
-    _uid: Option<u32>,
-    _gid: Option<u32>,
-    size: Option<u64>,
-    _atime: Option<fuser::TimeOrNow>,
-    _mtime: Option<fuser::TimeOrNow>,
-    _ctime: Option<SystemTime>,
-    _fh: Option<u64>,
-    _crtime: Option<SystemTime>,
-    _chgtime: Option<SystemTime>,
-    _bkuptime: Option<SystemTime>,
-    _flags: Option<u32>,
-    reply: ReplyAttr,
-    ) {
-        println!("setattr(ino={}, size={:?})", ino, size);
-    // Se viene richiesta una truncation, gestiscila (es. manda una chiamata al server)
-        if let Some(_new_size) = size {
-        // qui puoi chiamare l'API remota per troncare il file, oppure accettare e rispondere localmente
-        // per ora rispondiamo con attributi aggiornati (dummy)
-        
+        if let Err(errno) = result {
+            reply.error(errno);
+            return;
         }
+        self.cache.invalidate(&full_path);
 
-        let ts = SystemTime::now();
-        let attr = FileAttr {
-            ino,
-            size: size.unwrap_or(0),
-            blocks: 0,
-            atime: ts,
-            mtime: ts,
-            ctime: ts,
-            crtime: ts,
-            kind: FileType::RegularFile,
-            perm: 0o644,
-            nlink: 1,
-            uid: self.uid,
-            gid: self.gid,
-            rdev: 0,
-            flags: 0,
-            blksize: 512,
+        let Some(info) = self.lookup_info(&full_path) else {
+            reply.error(EIO);
+            return;
         };
-        reply.attr(&Duration::new(1,0), &attr);
+        let ino = self.register_path(&full_path, &info);
+        reply.entry(&self.cache.policy.kernel_ttl(), &self.file_attr(ino, &info), 0);
     }
 
-    fn write(
+    fn link(
         &mut self,
         _req: &Request<'_>,
         ino: u64,
-        _fh: u64,
-        _offset: i64,
-        data: &[u8],
-        _: u32,
-        _flags: i32,
-        _lock_owner: Option<u64>,
-        reply: ReplyWrite,
+        newparent: u64,
+        newname: &OsStr,
+        reply: ReplyEntry,
     ) {
-        
-        let path = self.get_path(ino).unwrap();
-        println!("execute write {}", path);
+        println!("link(ino={}, newparent={}, newname={:?})", ino, newparent, newname);
+
+        let (Some(source), Some(parent_path)) = (self.get_path(ino), self.get_path(newparent)) else {
+            reply.error(ENOENT);
+            return;
+        };
+        let full_path = format!("{}/{}", parent_path, newname.to_str().unwrap_or(""));
+
         let client = Client::new();
-        let token = self.token.clone();
+        let token = self.token();
         let base_url = self.base_url.clone();
-        let body = String::from_utf8_lossy(data).to_string();
+        let body = serde_json::json!({ "target": source });
 
-        let ok: bool = task::block_in_place(|| {
+        // the answer carries the attributes of the new name, with the updated link count
+        let result: Result<FileInfo, i32> = task::block_in_place(|| {
             let rt = tokio::runtime::Handle::current();
             rt.block_on(async {
                 let resp = client
-                    .put(format!("{}/files/{}", base_url, path))
-                    .bearer_auth(token)
-                    .body(body)
+                    .post(format!("{}/link/{}", base_url, full_path))
+                    .bearer_auth(&token)
+                    .json(&body)
                     .send()
                     .await;
 
                 match resp {
-                    Ok(r) => r.status().is_success(),
-                    Err(_) => false,
+                    Ok(r) if r.status().is_success() => {},
+                    Ok(r) => return Err(status_to_errno(r.status())),
+                    Err(_) => return Err(EIO),
+                }
+
+                let resp = client
+                    .get(format!("{}/lookup/{}", base_url, full_path))
+                    .bearer_auth(&token)
+                    .send()
+                    .await;
+                match resp {
+                    Ok(r) if r.status().is_success() => r.json::<FileInfo>().await.map_err(|_| EIO),
+                    Ok(r) => Err(status_to_errno(r.status())),
+                    Err(_) => Err(EIO),
                 }
             })
         });
 
-        if ok{
-            reply.written(data.len() as u32);
-        }else{
-            reply.error(EIO);
+        match result {
+            Ok(obj) => {
+                // the link count of the source changed too
+                self.cache.invalidate(&source);
+                self.cache.invalidate(&full_path);
+                // both names are the same file, the server gives them the same id and so the same inode
+                let ino = self.register_path(&full_path, &obj);
+                reply.entry(&self.cache.policy.kernel_ttl(), &self.file_attr(ino, &obj), 0);
+            }
+            Err(errno) => reply.error(errno),
         }
     }
 
-    fn flush(&mut self, _req: &Request, ino: u64, _fh: u64, _lock_owner: u64, reply: ReplyEmpty) {
-        println!("flush(ino={})", ino);
-        reply.ok(); // non serve fare nulla
-    }
-
-    fn fsync(&mut self, _req: &Request<'_>, ino: u64, fh: u64, datasync: bool, reply: ReplyEmpty) {
-        println!("fsync(ino={}, fh={}, datasync={})", ino, fh, datasync);
-        reply.ok();
-    }
-
-    fn release(&mut self, _req: &Request, ino: u64, _fh: u64, _flags: i32, _lock_owner: Option<u64>, _flush: bool, reply: ReplyEmpty) {
-        println!("release(ino={})", ino);
-
-        reply.ok(); // idem
-    }
-
-    
-    fn unlink(
-        &mut self, 
-        _req: &Request, 
-        parent: u64, 
-        name: &OsStr, 
-        reply: ReplyEmpty) 
-    {
-        println!("unlink(parent={}, name={:?})", parent, name);
+    fn readlink(&mut self, _req: &Request<'_>, ino: u64, reply: ReplyData) {
+        println!("readlink(ino={})", ino);
 
-        let Some(parent_path) = self.get_path(parent) else {
-        reply.error(ENOENT);
-        return;
+        let Some(path) = self.get_path(ino) else {
+            reply.error(ENOENT);
+            return;
         };
 
-        let full_path = format!("{}/{}", parent_path, name.to_str().unwrap());
-        println!("Deleting {}", full_path);
-
         let client = Client::new();
-        let token = self.token.clone();
+        let token = self.token();
         let base_url = self.base_url.clone();
 
-         task::block_in_place(|| {
+        let result: Result<Vec<u8>, i32> = task::block_in_place(|| {
             let rt = tokio::runtime::Handle::current();
             rt.block_on(async {
                 let resp = client
-                    .delete(format!("{}/files/{}", base_url, full_path))
+                    .get(format!("{}/readlink/{}", base_url, path))
                     .bearer_auth(token)
                     .send()
                     .await;
 
                 match resp {
-                    Ok(r) if r.status().is_success() => reply.ok(),
-                    _ => reply.error(EIO),
+                    Ok(r) if r.status().is_success() => r.bytes().await.map(|b| b.to_vec()).map_err(|_| EIO),
+                    Ok(r) => Err(status_to_errno(r.status())),
+                    Err(_) => Err(EIO),
                 }
-            });
+            })
         });
-        
+
+        match result {
+            Ok(target) => reply.data(&target),
+            Err(errno) => reply.error(errno),
+        }
     }
 
     fn rmdir(
@@ -697,10 +1806,10 @@ impl Filesystem for RemoteFS {
         println!("Removing directory {}", full_path);
 
         let client = Client::new();
-        let token = self.token.clone();
+        let token = self.token();
         let base_url = self.base_url.clone();
 
-        task::block_in_place(|| {
+        let removed = task::block_in_place(|| {
             let rt = tokio::runtime::Handle::current();
             rt.block_on(async {
                 let resp = client
@@ -709,12 +1818,20 @@ impl Filesystem for RemoteFS {
                     .send()
                     .await;
 
-                match resp {
-                    Ok(r) if r.status().is_success() => reply.ok(),
-                    _ => reply.error(EIO),
-                }
-            });
+                matches!(resp, Ok(r) if r.status().is_success())
+            })
         });
+
+        if removed {
+            self.forget_path(&full_path);
+            self.cache.invalidate_tree(&full_path);
+            if let Some(content) = self.content.as_mut() {
+                content.invalidate_tree(&full_path);
+            }
+            reply.ok();
+        } else {
+            reply.error(EIO);
+        }
     }
 
 
@@ -730,4 +1847,55 @@ impl Drop for RemoteFS {
     }
 }
 
-}
\ No newline at end of file
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERVER: &str = "http://127.0.0.1:8080";
+
+    // like the tests of the server, it needs the server running on 127.0.0.1:8080
+    async fn login(username: &str) -> String {
+        let client = Client::new();
+        let credentials = serde_json::json!({ "username": username, "password": "password" });
+        let _ = client.post(format!("{}/auth/register", SERVER)).json(&credentials).send().await;
+        let res: serde_json::Value = client.post(format!("{}/auth/login", SERVER))
+            .json(&credentials)
+            .send()
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+        res["token"].as_str().unwrap().to_string()
+    }
+
+    #[tokio::test(flavor = "multi_thread")]
+    async fn test_buffered_write_bigger_than_body_limit() {
+        let token = login("writebackuser").await;
+        let client = Client::new();
+        let res = client.put(format!("{}/files/big.bin", SERVER)).bearer_auth(&token).send().await.unwrap();
+        assert!(res.status().is_success());
+
+        let mut fs = RemoteFS::new(SERVER.to_string(), token.clone(), 0, 0);
+        fs.inode_to_path.insert(2, "/big.bin".to_string());
+        let fh = fs.open_handle(2, libc::O_WRONLY);
+
+        // 3 MiB in the chunks of the kernel, merged in a single extent by the handle
+        let data: Vec<u8> = (0..3 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+        for (i, chunk) in data.chunks(128 * 1024).enumerate() {
+            fs.handles.get_mut(&fh).unwrap().add((i * 128 * 1024) as u64, chunk);
+        }
+        assert_eq!(fs.sync_handle(fh), Ok(()));
+
+        let res = client.get(format!("{}/files/big.bin", SERVER)).bearer_auth(&token).send().await.unwrap();
+        let content = res.bytes().await.unwrap();
+        assert_eq!(content.len(), data.len());
+        assert!(content[..] == data[..]);
+
+        let res = client.delete(format!("{}/files/big.bin", SERVER)).bearer_auth(&token).send().await.unwrap();
+        assert!(res.status().is_success());
+    }
+}
+
+}
@@ -1,12 +1,11 @@
 
-use client::fuse_mod::RemoteFS;
+use client::fuse_mod::{CachePolicy, ContentCache, RemoteFS};
 use serde::{Deserialize, Serialize};
 use std::io::{self, Write};
 use rpassword::read_password;
 use reqwest::Client;
 use users::{get_user_by_name};
 use std::process::Command;
-use daemonize::Daemonize;
 
 #[derive(Serialize)]
 struct LoginRequest {
@@ -17,6 +16,8 @@ struct LoginRequest {
 #[derive(Deserialize, Debug)]
 struct LoginResponse {
     token: String,
+    refresh_token: String,
+    expires_in: u64,
 }
 
 
@@ -36,6 +37,39 @@ fn ensure_unmounted(mountpoint: &str) {
     }
 }
 
+// cache of attributes and directories: --cache none | ttl:<seconds> | lru:<entries> (default ttl:1)
+fn cache_policy_from_args() -> Result<CachePolicy, String> {
+    match arg_value("--cache") {
+        Some(policy) => policy.parse(),
+        None if std::env::args().any(|a| a == "--cache") => Err("--cache needs a value".to_string()),
+        None => Ok(CachePolicy::Ttl(std::time::Duration::from_secs(1))),
+    }
+}
+
+// value of a command line option, e.g. "--cache ttl:5"
+fn arg_value(name: &str) -> Option<String> {
+    let args: Vec<String> = std::env::args().collect();
+    args.iter().position(|a| a == name).and_then(|i| args.get(i + 1).cloned())
+}
+
+// copies of the files on the local disk: --content-cache <dir> | none (default ~/.cache/remote-fs),
+// --content-cache-size <MiB> (default 256). Every user has its own subdirectory
+fn content_cache_from_args(username: &str) -> Result<Option<ContentCache>, String> {
+    let dir = match arg_value("--content-cache") {
+        Some(dir) if dir == "none" => return Ok(None),
+        Some(dir) => std::path::PathBuf::from(dir),
+        None => std::path::PathBuf::from(std::env::var("HOME").unwrap_or_else(|_| ".".to_string()))
+            .join(".cache/remote-fs"),
+    };
+    let size_mib: u64 = match arg_value("--content-cache-size") {
+        Some(size) => size.parse().map_err(|_| format!("invalid content cache size '{}', it must be a number of MiB", size))?,
+        None => 256,
+    };
+    ContentCache::open(&dir.join(username), size_mib * 1024 * 1024)
+        .map(Some)
+        .map_err(|e| format!("can't use {} for the content cache: {}", dir.display(), e))
+}
+
 // funzione per assicurare che l'utente locale esista
 fn ensure_local_user(username: &str) -> (u32, u32) {
     if let Some(user) = get_user_by_name(username) {
@@ -71,6 +105,8 @@ fn ensure_local_user(username: &str) -> (u32, u32) {
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
     
     println!("== Remote FS ==");
+    let cache_policy = cache_policy_from_args()?;
+    println!("Cache: {:?}", cache_policy);
 
     //login or registration
     let mut account= false;
@@ -80,9 +116,9 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         let mut answer = String::new();
         io::stdin().read_line(&mut answer)?;
         let answer = answer.trim().to_uppercase().to_string();
-        if  answer=="Y".to_string(){
+        if  answer=="Y"{
                 account=true;
-        }else if answer=="N".to_string(){
+        }else if answer=="N"{
                 //Registratrion 
                 println!("== Registration ==");
                     // Input username
@@ -150,7 +186,14 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         let (uid, gid) = ensure_local_user(&current_user);
         println!("Utente locale '{}' → UID={}, GID={}", current_user.clone(), uid, gid);
         
-        let fs = RemoteFS::new("http://127.0.0.1:8080".to_string(), token, uid, gid);
+        let mut fs = RemoteFS::new("http://127.0.0.1:8080".to_string(), token, uid, gid);
+        fs.set_refresh_token(login_res.refresh_token, login_res.expires_in);
+        fs.set_cache_policy(cache_policy);
+        // --write-through: every write is sent at once instead of at the close of the file
+        fs.set_write_through(std::env::args().any(|a| a == "--write-through"));
+        if let Some(content) = content_cache_from_args(&current_user)? {
+            fs.set_content_cache(content);
+        }
         let mountpoint = "/home/irene/progetto_rust_filesystem/client/mount";
         ensure_unmounted(mountpoint);
         println!("Mounting Remote FS at {}", mountpoint);
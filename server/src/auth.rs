@@ -1,10 +1,11 @@
 use serde::{Deserialize, Serialize};
 use jsonwebtoken::{decode, encode, Algorithm, DecodingKey, EncodingKey, Header, Validation};
 use bcrypt::{hash, verify, DEFAULT_COST};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::sync::{Arc, Mutex};
 use chrono::{Duration, Utc};
-use rusqlite::{Connection,  params, Result as SQLResult};
+use rusqlite::{Connection, OptionalExtension, params, Result as SQLResult};
+use server::{FileSystem, QuotaUsage};
 
 // Struttura per i claims del JWT
 #[derive(Debug, Serialize, Deserialize)]
@@ -13,6 +14,8 @@ pub struct Claims {
     pub user_id: i32,       // user_id
     pub exp: usize,         // expiration time
     pub iat: usize,         // issued at
+    #[serde(default)]
+    pub jti: String,        // id of the token, used to revoke it
 }
 
 // Struttura per l'utente
@@ -21,6 +24,8 @@ pub struct User {
     pub username: String,
     pub password_hash: String,
     pub user_id: Option<i32>,
+    #[serde(default)]
+    pub is_admin: bool,
 }
 
 // Richiesta di login
@@ -37,42 +42,231 @@ pub struct RegisterRequest {
     pub password: String,
 }
 
+// Richiesta di refresh
+#[derive(Debug, Deserialize)]
+pub struct RefreshRequest {
+    pub refresh_token: String,
+}
+
+// Richiesta di logout: con il refresh token si chiude solo quella sessione
+#[derive(Debug, Deserialize)]
+pub struct LogoutRequest {
+    pub refresh_token: Option<String>,
+}
+
+// Richiesta di cambio password
+#[derive(Debug, Deserialize)]
+pub struct ChangePasswordRequest {
+    pub old_password: String,
+    pub new_password: String,
+}
+
+// Richiesta di cancellazione del proprio account
+#[derive(Debug, Deserialize)]
+pub struct DeleteAccountRequest {
+    pub password: String,
+    #[serde(default)]
+    pub purge: bool,        // also remove the files and their metadata
+}
+
+// body of PUT /admin/users/:username/quota, null (or missing) means unlimited
+#[derive(Debug, Deserialize)]
+pub struct QuotaRequest {
+    pub max_bytes: Option<u64>,
+    pub max_items: Option<u64>,
+}
+
+// an account as returned by GET /admin/users
+#[derive(Debug, Serialize)]
+pub struct AccountInfo {
+    pub username: String,
+    pub user_id: i32,
+    pub is_admin: bool,
+}
+
 // Risposta di autenticazione
 #[derive(Debug, Serialize)]
 pub struct AuthResponse {
     pub token: String,
+    pub refresh_token: String,      // single use, POST /auth/refresh gives a new pair
     pub username: String,
     pub user_id: i32,
-    pub expires_in: usize,
+    pub expires_in: usize,          // seconds of validity of the token
 }
 
 // Database utenti 
 pub type UserDB = Arc<Mutex<HashMap<String, User>>>;
 
-// Chiave segreta per firmare i JWT (in produzione usare variabile ambiente)
-const JWT_SECRET: &str = "malnati-e-bello";
+// The key that signs the JWT comes from JWT_SECRET or from the file in JWT_SECRET_FILE.
+// If neither exists a random key is generated and saved in the file, so the tokens survive a restart
+const DEFAULT_JWT_SECRET_FILE: &str = "database/jwt_secret.key";
+
+// validity of the access tokens and of the refresh tokens
+const ACCESS_TOKEN_TTL: Duration = Duration::minutes(15);
+const REFRESH_TOKEN_TTL: Duration = Duration::days(30);
 
 pub struct AuthService {
     users: UserDB,
-    conn: Arc<Mutex<Connection>>
+    conn: Arc<Mutex<Connection>>,
+    admin_usernames: Vec<String>,   // from ADMIN_USERS (comma separated), reserved names with the admin role
+    jwt_secret: Vec<u8>,
+    revoked: Mutex<HashSet<String>>,    // jti of the revoked tokens not yet expired (copy of REVOKED_TOKEN)
+    default_quota: (Option<u64>, Option<u64>),  // bytes and items of the new accounts, from DEFAULT_QUOTA_BYTES/ITEMS
 }
 
 impl AuthService {
     pub fn new( conn: Arc<Mutex<Connection>>) -> Self {
+        // no default: without ADMIN_USERS the server has no admins
+        let admin_usernames: Vec<String> = std::env::var("ADMIN_USERS")
+            .unwrap_or_default()
+            .split(',')
+            .map(|u| u.trim().to_string())
+            .filter(|u| !u.is_empty())
+            .collect();
+
         let u = AuthService::load_from_db(conn.clone());
         let users_map = u.unwrap_or_default();
-        Self {
+        let revoked = AuthService::load_revoked(&conn).unwrap_or_else(|e| {
+            println!("⚠️  Failed to load the revoked tokens: {}", e);
+            HashSet::new()
+        });
+        let service = Self {
             users: Arc::new(Mutex::new(users_map)),
-            conn: conn.clone()
+            conn: conn.clone(),
+            admin_usernames,
+            jwt_secret: AuthService::load_jwt_secret(),
+            revoked: Mutex::new(revoked),
+            default_quota: (Self::quota_from_env("DEFAULT_QUOTA_BYTES"), Self::quota_from_env("DEFAULT_QUOTA_ITEMS")),
+        };
+        service.bootstrap_admins(std::env::var("ADMIN_PASSWORD").ok());
+        service
+    }
+
+    fn load_jwt_secret() -> Vec<u8> {
+        if let Ok(secret) = std::env::var("JWT_SECRET") {
+            if !secret.is_empty() {
+                return secret.into_bytes();
+            }
+        }
+
+        let path = std::env::var("JWT_SECRET_FILE").unwrap_or_else(|_| DEFAULT_JWT_SECRET_FILE.to_string());
+        if let Ok(secret) = std::fs::read_to_string(&path) {
+            let secret = secret.trim();
+            if !secret.is_empty() {
+                return secret.as_bytes().to_vec();
+            }
+        }
+
+        let secret = format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple());
+        if let Some(dir) = std::path::Path::new(&path).parent() {
+            let _ = std::fs::create_dir_all(dir);
+        }
+        match std::fs::write(&path, &secret) {
+            Ok(_) => {
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    let _ = std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600));
+                }
+                println!("🔑 New JWT signing key saved in {}", path);
+            },
+            Err(e) => println!("⚠️  Failed to save the JWT signing key in {}: {}, the tokens won't survive a restart", path, e),
         }
+        secret.into_bytes()
+    }
+
+    // unset or not a number means unlimited
+    fn quota_from_env(name: &str) -> Option<u64> {
+        let value = std::env::var(name).ok()?;
+        match value.trim().parse() {
+            Ok(limit) => Some(limit),
+            Err(_) => {
+                println!("⚠️  Ignoring {}={}, it must be a number", name, value);
+                None
+            },
+        }
+    }
+
+    // the expired rows are not needed anymore: an expired token is refused anyway
+    fn load_revoked(conn: &Arc<Mutex<Connection>>) -> Result<HashSet<String>, String> {
+        let conn = conn.lock().unwrap();
+        let now = Utc::now().timestamp();
+        conn.execute("DELETE FROM REVOKED_TOKEN WHERE Expires_At < ?1", params![now])
+            .map_err(|e| e.to_string())?;
+        conn.execute("DELETE FROM REFRESH_TOKEN WHERE Expires_At < ?1", params![now])
+            .map_err(|e| e.to_string())?;
+
+        let mut stmt = conn.prepare("SELECT Jti FROM REVOKED_TOKEN").map_err(|e| e.to_string())?;
+        let rows = stmt.query_map([], |row| row.get::<_, String>(0)).map_err(|e| e.to_string())?;
+        rows.collect::<Result<_, _>>().map_err(|e| e.to_string())
+    }
+
+    // the users listed in ADMIN_USERS become admins; the missing ones are created at startup with
+    // ADMIN_PASSWORD, never by /auth/register, so nobody can take an admin name by registering first
+    fn bootstrap_admins(&self, admin_password: Option<String>) {
+        let mut users = self.users.lock().unwrap();
+        for username in &self.admin_usernames {
+            match users.get_mut(username) {
+                Some(user) if user.is_admin => {},
+                Some(user) => {
+                    let conn = self.conn.lock().unwrap();
+                    if let Err(e) = conn.execute("UPDATE USER SET Is_Admin = 1 WHERE Username = ?1", params![username]) {
+                        println!("⚠️  Failed to promote {} to admin: {}", username, e);
+                        continue;
+                    }
+                    user.is_admin = true;
+                    println!("👑 {} is an admin", username);
+                }
+                None => {
+                    let Some(password) = admin_password.as_ref().filter(|p| p.len() >= 6) else {
+                        println!("⚠️  Admin {} not created: ADMIN_PASSWORD is missing or shorter than 6 characters", username);
+                        continue;
+                    };
+                    let mut user = User {
+                        username: username.clone(),
+                        password_hash: match hash(password, DEFAULT_COST) {
+                            Ok(h) => h,
+                            Err(e) => {
+                                println!("⚠️  Failed to hash the password of {}: {}", username, e);
+                                continue;
+                            }
+                        },
+                        user_id: None,
+                        is_admin: true,
+                    };
+                    match self.save_to_db(user.clone()) {
+                        Ok(user_id) => {
+                            user.user_id = Some(user_id);
+                            users.insert(username.clone(), user);
+                            println!("👑 Admin {} created", username);
+                        }
+                        Err(e) => println!("⚠️  Failed to create the admin {}: {}", username, e),
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn is_admin(&self, user_id: i32) -> bool {
+        let users = self.users.lock().unwrap();
+        users.values().any(|u| u.user_id == Some(user_id) && u.is_admin)
+    }
+
+    // id of a registered user
+    pub fn user_id(&self, username: &str) -> Option<i32> {
+        let users = self.users.lock().unwrap();
+        users.get(username).and_then(|u| u.user_id)
     }
 
     // Registra un nuovo utente
     pub fn register(&self, req: RegisterRequest) -> Result<String, String> {
-        let mut users = self.users.lock().unwrap();
-        
+        // the admin accounts are created only at startup (see bootstrap_admins)
+        if self.admin_usernames.contains(&req.username) {
+            return Err("Username reserved for an administrator".to_string());
+        }
+
         // Controlla se l'utente esiste già
-        if users.contains_key(&req.username) {
+        if self.users.lock().unwrap().contains_key(&req.username) {
             return Err("Username already exists".to_string());
         }
 
@@ -81,15 +275,22 @@ impl AuthService {
             return Err("Password must be at least 6 characters".to_string());
         }
 
-        // Hash della password
+        // Hash della password, senza tenere il lock sugli utenti (bcrypt è lento)
         let password_hash = hash(&req.password, DEFAULT_COST)
             .map_err(|_| "Failed to hash password")?;
 
+        // another registration with the same name may have finished in the meantime
+        let mut users = self.users.lock().unwrap();
+        if users.contains_key(&req.username) {
+            return Err("Username already exists".to_string());
+        }
+
         // Crea l'utente senza ID (sarà generato dal DB)
         let mut user = User {
             username: req.username.clone(),
             password_hash,
-            user_id: None
+            user_id: None,
+            is_admin: false,
         };
 
         // Salva nel DB e ottieni l'ID generato
@@ -103,17 +304,131 @@ impl AuthService {
         Ok("User registered successfully".to_string())
     }
 
+    // the other sessions have to log in again with the new password
+    pub fn change_password(&self, user_id: i32, req: ChangePasswordRequest) -> Result<(), String> {
+        // bcrypt is slow: it runs on a copy of the hash, without the lock on the users
+        let old_hash = self.password_hash(user_id)?;
+        let is_valid = verify(&req.old_password, &old_hash)
+            .map_err(|_| "Authentication failed")?;
+        if !is_valid {
+            return Err("Permission denied: wrong password".to_string());
+        }
+        if req.new_password.len() < 6 {
+            return Err("Invalid password: it must be at least 6 characters".to_string());
+        }
+
+        let password_hash = hash(&req.new_password, DEFAULT_COST)
+            .map_err(|_| "Failed to hash password")?;
+
+        let mut users = self.users.lock().unwrap();
+        let user = users.values_mut()
+            .find(|u| u.user_id == Some(user_id))
+            .ok_or(format!("User {} not found", user_id))?;
+        // another change finished in the meantime, the old password checked above is not valid anymore
+        if user.password_hash != old_hash {
+            return Err("Permission denied: the password was changed in the meantime".to_string());
+        }
+        let conn = self.conn.lock().unwrap();
+        conn.execute("UPDATE USER SET Password = ?1 WHERE User_ID = ?2", params![password_hash, user_id])
+            .map_err(|e| format!("Database error: {}", e))?;
+        conn.execute("DELETE FROM REFRESH_TOKEN WHERE User_ID = ?1", params![user_id])
+            .map_err(|e| format!("Database error: {}", e))?;
+        user.password_hash = password_hash;
+
+        println!("🔑 Password changed for {}", user.username);
+        Ok(())
+    }
+
+    pub fn check_password(&self, user_id: i32, password: &str) -> Result<(), String> {
+        let password_hash = self.password_hash(user_id)?;
+        match verify(password, &password_hash) {
+            Ok(true) => Ok(()),
+            _ => Err("Permission denied: wrong password".to_string()),
+        }
+    }
+
+    // copy of the hash of a user, so that bcrypt doesn't run with the users locked
+    fn password_hash(&self, user_id: i32) -> Result<String, String> {
+        let users = self.users.lock().unwrap();
+        users.values()
+            .find(|u| u.user_id == Some(user_id))
+            .map(|u| u.password_hash.clone())
+            .ok_or(format!("User {} not found", user_id))
+    }
+
+    // Removes the account, its sessions and its group memberships.
+    // The items it created in the trees of the other users go to the owners of those trees.
+    // With purge the METADATA of its tree is deleted too (the files are removed by the registry),
+    // otherwise it stays with the archived files
+    pub fn delete_user(&self, username: &str, purge: bool) -> Result<i32, String> {
+        let mut users = self.users.lock().unwrap();
+        let user_id = users.get(username)
+            .and_then(|u| u.user_id)
+            .ok_or(format!("User {} not found", username))?;
+
+        let mut conn = self.conn.lock().unwrap();
+        let result = (|| -> SQLResult<()> {
+            let tx = conn.transaction()?;
+            tx.execute("UPDATE METADATA SET owner_id = NULL WHERE owner_id = ?1", params![user_id])?;
+            if purge {
+                tx.execute("DELETE FROM METADATA WHERE user_id = ?1", params![user_id])?;
+            }
+            tx.execute("DELETE FROM GROUP_MEMBER WHERE User_ID = ?1", params![user_id])?;
+            tx.execute("DELETE FROM REFRESH_TOKEN WHERE User_ID = ?1", params![user_id])?;
+            tx.execute("DELETE FROM USER WHERE User_ID = ?1", params![user_id])?;
+            tx.commit()
+        })();
+        result.map_err(|e| format!("Database error: {}", e))?;
+        users.remove(username);
+
+        println!("🗑️  Account {} (id: {}) deleted", username, user_id);
+        Ok(user_id)
+    }
+
+    // the new limits apply to the next writes, what is already stored is kept even if over the quota
+    pub fn set_quota(&self, username: &str, req: &QuotaRequest) -> Result<i32, String> {
+        let user_id = self.user_id(username).ok_or(format!("User {} not found", username))?;
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "UPDATE USER SET Quota_Bytes = ?1, Quota_Items = ?2 WHERE User_ID = ?3",
+            params![req.max_bytes.map(|b| b as i64), req.max_items.map(|i| i as i64), user_id],
+        ).map_err(|e| format!("Database error: {}", e))?;
+        println!("📏 Quota of {}: {:?} bytes, {:?} items", username, req.max_bytes, req.max_items);
+        Ok(user_id)
+    }
+
+    pub fn quota_usage(&self, user_id: i32) -> Result<QuotaUsage, String> {
+        let conn = self.conn.lock().unwrap();
+        FileSystem::quota_usage(&conn, user_id as i64)
+    }
+
+    pub fn list_accounts(&self) -> Vec<AccountInfo> {
+        let users = self.users.lock().unwrap();
+        let mut accounts: Vec<AccountInfo> = users.values()
+            .filter_map(|u| u.user_id.map(|user_id| AccountInfo {
+                username: u.username.clone(),
+                user_id,
+                is_admin: u.is_admin,
+            }))
+            .collect();
+        accounts.sort_by(|a, b| a.username.cmp(&b.username));
+        accounts
+    }
+
     // Login utente
+    // bcrypt is slow on purpose: call it from a blocking thread (see the login handler)
     pub fn login(&self, req: LoginRequest) -> Result<AuthResponse, String> {
 
-        let users = self.users.lock().unwrap();
-        
-        // Trova l'utente
-        let user = users.get(&req.username)
-            .ok_or("Invalid username or password")?;
+        // Trova l'utente, il lock è rilasciato prima di verificare la password
+        let (password_hash, user_id) = {
+            let users = self.users.lock().unwrap();
+            let user = users.get(&req.username)
+                .ok_or("Invalid username or password")?;
+            (user.password_hash.clone(), user.user_id)
+        };
 
         // Verifica la password
-        let is_valid = verify(&req.password, &user.password_hash)
+        let is_valid = verify(&req.password, &password_hash)
             .map_err(|_| "Authentication failed")?;
 
         if !is_valid {
@@ -123,7 +438,7 @@ impl AuthService {
         // ensure there is a user directory
         self.ensure_user_directory(&req.username)?;
 
-        let user_id = match user.user_id {
+        let user_id = match user_id {
             Some(id) => id,
             None => {
                 // Se user_id non è in memoria, cerca nel database
@@ -140,16 +455,89 @@ impl AuthService {
         };
 
         // ✅ GENERA: token con user_id incluso
-        let token = self.generate_token(&req.username, user_id)?;
-        
+        self.issue_tokens(&req.username, user_id)
+    }
+
+    // a new access token and a new refresh token
+    fn issue_tokens(&self, username: &str, user_id: i32) -> Result<AuthResponse, String> {
+        let token = self.generate_token(username, user_id)?;
+
+        let refresh_token = format!("{}{}", uuid::Uuid::new_v4().simple(), uuid::Uuid::new_v4().simple());
+        let expires_at = (Utc::now() + REFRESH_TOKEN_TTL).timestamp();
+        self.conn.lock().unwrap().execute(
+            "INSERT INTO REFRESH_TOKEN (Token, User_ID, Expires_At) VALUES (?1, ?2, ?3)",
+            params![refresh_token, user_id, expires_at],
+        ).map_err(|e| format!("Database error: {}", e))?;
+
         Ok(AuthResponse {
             token,
-            username: req.username,
+            refresh_token,
+            username: username.to_string(),
             user_id,
-            expires_in: 3600, // 1 ora
+            expires_in: ACCESS_TOKEN_TTL.num_seconds() as usize,
         })
     }
 
+    // a refresh token can be used only once: it is replaced by the one in the answer
+    pub fn refresh(&self, req: RefreshRequest) -> Result<AuthResponse, String> {
+        let (user_id, expires_at): (i32, i64) = {
+            let conn = self.conn.lock().unwrap();
+            let row = conn.query_row(
+                "SELECT User_ID, Expires_At FROM REFRESH_TOKEN WHERE Token = ?1",
+                params![req.refresh_token],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            ).optional().map_err(|e| format!("Database error: {}", e))?
+                .ok_or("Invalid refresh token")?;
+            conn.execute("DELETE FROM REFRESH_TOKEN WHERE Token = ?1", params![req.refresh_token])
+                .map_err(|e| format!("Database error: {}", e))?;
+            row
+        };
+        if expires_at < Utc::now().timestamp() {
+            return Err("Refresh token expired".to_string());
+        }
+
+        let username = {
+            let users = self.users.lock().unwrap();
+            users.values()
+                .find(|u| u.user_id == Some(user_id))
+                .map(|u| u.username.clone())
+                .ok_or("Invalid refresh token")?
+        };
+        self.issue_tokens(&username, user_id)
+    }
+
+    // the token stops working immediately; with a refresh token only that session is closed,
+    // without one all the refresh tokens of the user are dropped
+    pub fn logout(&self, token: &str, refresh_token: Option<&str>) -> Result<(), String> {
+        let claims = self.decode_token(token)?;
+
+        let conn = self.conn.lock().unwrap();
+        conn.execute(
+            "INSERT OR IGNORE INTO REVOKED_TOKEN (Jti, Expires_At) VALUES (?1, ?2)",
+            params![claims.jti, claims.exp as i64],
+        ).map_err(|e| format!("Database error: {}", e))?;
+        match refresh_token {
+            Some(refresh_token) => conn.execute(
+                "DELETE FROM REFRESH_TOKEN WHERE Token = ?1 AND User_ID = ?2",
+                params![refresh_token, claims.user_id],
+            ),
+            None => conn.execute("DELETE FROM REFRESH_TOKEN WHERE User_ID = ?1", params![claims.user_id]),
+        }.map_err(|e| format!("Database error: {}", e))?;
+        drop(conn);
+
+        self.revoked.lock().unwrap().insert(claims.jti);
+        println!("👋 {} logged out", claims.sub);
+        Ok(())
+    }
+
+    // username and id of every registered user
+    pub fn list_users(&self) -> Vec<(String, i32)> {
+        let users = self.users.lock().unwrap();
+        users.values()
+            .filter_map(|u| u.user_id.map(|id| (u.username.clone(), id)))
+            .collect()
+    }
+
     // function to create the user directory
     fn ensure_user_directory(&self, username: &str) -> Result<(), String> {
         use std::fs;
@@ -176,7 +564,7 @@ impl AuthService {
     // Genera JWT token
     fn generate_token(&self, username: &str, user_id: i32) -> Result<String, String> {
         let expiration = Utc::now()
-            .checked_add_signed(Duration::hours(1))
+            .checked_add_signed(ACCESS_TOKEN_TTL)
             .expect("valid timestamp")
             .timestamp() as usize;
 
@@ -185,34 +573,57 @@ impl AuthService {
             user_id,  // ✅ INCLUDI user_id nel token
             exp: expiration,
             iat: Utc::now().timestamp() as usize,
+            jti: uuid::Uuid::new_v4().to_string(),
         };
 
         encode(
             &Header::default(),
             &claims,
-            &EncodingKey::from_secret(JWT_SECRET.as_ref()),
+            &EncodingKey::from_secret(&self.jwt_secret),
         )
         .map_err(|_| "Failed to generate token".to_string())
     }
 
-    // Valida JWT token
-    pub fn validate_token(&self, token: &str) -> Result<(String, i32), String> {
+    // claims of a valid token that has not been revoked
+    fn decode_token(&self, token: &str) -> Result<Claims, String> {
         let token_data = decode::<Claims>(
             token,
-            &DecodingKey::from_secret(JWT_SECRET.as_ref()),
+            &DecodingKey::from_secret(&self.jwt_secret),
             &Validation::new(Algorithm::HS256),
         )
         .map_err(|_| "Invalid token".to_string())?;
 
-        Ok((token_data.claims.sub, token_data.claims.user_id))
+        if self.revoked.lock().unwrap().contains(&token_data.claims.jti) {
+            return Err("Invalid token: revoked".to_string());
+        }
+        // the tokens of a deleted account stop working
+        let exists = self.users.lock().unwrap()
+            .get(&token_data.claims.sub)
+            .is_some_and(|u| u.user_id == Some(token_data.claims.user_id));
+        if !exists {
+            return Err("Invalid token: the account doesn't exist".to_string());
+        }
+        Ok(token_data.claims)
+    }
+
+    // Valida JWT token
+    pub fn validate_token(&self, token: &str) -> Result<(String, i32), String> {
+        let claims = self.decode_token(token)?;
+        Ok((claims.sub, claims.user_id))
     }
 
     // Salva utenti su DB 
     pub fn save_to_db(&self, user: User) -> SQLResult<i32> {
         let conn = self.conn.lock().unwrap();
         conn.execute(
-            "INSERT INTO USER (Username, Password) VALUES (?1, ?2)", 
-            params![user.username, user.password_hash],
+            "INSERT INTO USER (Username, Password, Is_Admin, Quota_Bytes, Quota_Items) VALUES (?1, ?2, ?3, ?4, ?5)",
+            params![
+                user.username,
+                user.password_hash,
+                user.is_admin,
+                self.default_quota.0.map(|b| b as i64),
+                self.default_quota.1.map(|i| i as i64),
+            ],
         )?;
         
         let user_id = conn.last_insert_rowid() as i32;
@@ -223,7 +634,7 @@ impl AuthService {
     pub fn load_from_db(conn: Arc<Mutex<Connection>>) -> Result<HashMap<String, User>, String> {
         let c = conn.lock().unwrap();
         // ✅ USA: i nomi corretti delle colonne (Username, Password, User_ID)
-        let stmt = c.prepare("SELECT Username, Password, User_ID FROM USER");
+        let stmt = c.prepare("SELECT Username, Password, User_ID, Is_Admin FROM USER");
         match stmt {
             Ok(mut statement) => {
                 let mut user_map = HashMap::new();
@@ -231,10 +642,12 @@ impl AuthService {
                     let username: String = row.get(0)?;
                     let password_hash: String = row.get(1)?;
                     let user_id: i32 = row.get(2)?;
+                    let is_admin: bool = row.get(3)?;
                     Ok(User {
                         username,
                         password_hash,
                         user_id: Some(user_id),
+                        is_admin,
                     })
                 }).map_err(|e| e.to_string())?;
 
@@ -1,10 +1,12 @@
 pub mod filesystem_mod{
 
 use std::sync::{Arc, Mutex, Weak};
+use std::collections::{HashMap, HashSet};
 use std::ops::Deref;
 use std::path::PathBuf;
 use std::path::Path;
 use std::fs::{self, OpenOptions};
+use std::io::{Read, Seek, SeekFrom, Write};
 use walkdir::WalkDir;
 use rusqlite::{params, Connection, Result as SqlResult};
 use rusqlite::OptionalExtension;
@@ -62,6 +64,14 @@ impl FSItem {
         }
     }
 
+    pub fn set_parent(&mut self, parent: FSNodeWeak) {
+        match self {
+            FSItem::File(f) => f.parent = parent,
+            FSItem::Directory(d) => d.parent = parent,
+            FSItem::SymLink(s) => s.parent = parent,
+        }
+    }
+
     pub fn set_name(&mut self, name: &str) {
         match self {
             FSItem::File(f) => f.name = name.to_owned(),
@@ -91,6 +101,15 @@ impl FSItem {
 
 }
 
+// group shown for the items that don't belong to any group
+const DEFAULT_GROUP_NAME: &str = "users";
+
+// same limit of the linux kernel (ELOOP)
+const MAX_LINK_DEPTH: usize = 40;
+
+// uploads are received in ".<name>.upload-<uuid>" next to the destination
+const UPLOAD_SUFFIX: &str = ".upload-";
+
 type FSItemCell = Mutex<FSItem>;
 type FSNode = Arc<FSItemCell>;
 type FSNodeWeak = Weak<FSItemCell>;
@@ -148,13 +167,23 @@ impl FileMetadata {
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct FileInfo {
     pub permissions: u16,        // es: 0o755
-    pub links: u32,                 // always 1
+    pub links: u32,                 // hard links of a file, 2 + subdirectories for a directory
     pub owner: String,              // owner username
-    pub group: String,              // group (always users)
+    pub group: String,              // name of the group of the item ("users" if it has none)
     pub size: i64,                  // dimension in bytes
-    pub modified: String,           // last modifiied date
+    pub modified: String,           // last modifiied date (RFC3339, like the other times)
+    #[serde(default)]
+    pub accessed: String,           // last read of the content
+    #[serde(default)]
+    pub changed: String,            // last change of content or metadata (ctime)
+    #[serde(default)]
+    pub created: String,            // creation date
     pub name: String,               // name of the file/directory
     pub is_directory: bool,         // flag to identify wether it is a directory or not
+    #[serde(default)]
+    pub is_symlink: bool,           // the item is a symbolic link (see GET /readlink)
+    #[serde(default)]
+    pub file_id: Option<i64>,       // METADATA id, kept by renames and the same for all the hard links (None if virtual)
 }
 
 impl FileInfo {
@@ -168,17 +197,32 @@ impl FileInfo {
     ) -> Self {
         Self {
             permissions,
-            links: 1,  // always 1
+            links: 1,  // the real count is filled in by the filesystem (see link_count)
             owner,
-            group: "users".to_string(),  // always the same group "users"
+            group: DEFAULT_GROUP_NAME.to_string(),
             size,
+            accessed: modified.clone(),
+            changed: modified.clone(),
+            created: modified.clone(),
             modified,
             name,
             is_directory,
+            is_symlink: false,
+            file_id: None,
         }
     }
 }
 
+// space used by the items in the tree of a user, whoever owns them, and the limits of the account.
+// A limit set to None means unlimited
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct QuotaUsage {
+    pub used_bytes: u64,            // sum of the sizes of the files, every name of a hard link counts
+    pub used_items: u64,            // files, directories and links
+    pub max_bytes: Option<u64>,
+    pub max_items: Option<u64>,
+}
+
 pub struct File {
     name: String,
     size: usize,
@@ -197,12 +241,46 @@ pub struct SymLink {
     parent: FSNodeWeak,
 }
 
+// result of the reconciliation between disk, in-memory tree and METADATA table
+#[derive(Debug, Default)]
+pub struct ReconcileReport {
+    pub inserted: Vec<String>,       // found on disk, metadata created with default permissions
+    pub pruned: Vec<String>,         // metadata removed, the item is not on disk anymore
+    pub updated: Vec<String>,        // size or last_modified aligned to the disk
+    pub tree_added: Vec<String>,     // on disk but missing from the in-memory tree
+    pub tree_removed: Vec<String>,   // in the in-memory tree but not on disk
+}
+
+impl ReconcileReport {
+    pub fn is_clean(&self) -> bool {
+        self.inserted.is_empty() && self.pruned.is_empty() && self.updated.is_empty()
+            && self.tree_added.is_empty() && self.tree_removed.is_empty()
+    }
+}
+
+impl std::fmt::Display for ReconcileReport {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.is_clean() {
+            return write!(f, "disk, tree and metadata are consistent");
+        }
+        writeln!(f, "inserted: {}, pruned: {}, updated: {}, tree added: {}, tree removed: {}",
+            self.inserted.len(), self.pruned.len(), self.updated.len(), self.tree_added.len(), self.tree_removed.len())?;
+        for path in &self.inserted { writeln!(f, "   + {}", path)?; }
+        for path in &self.pruned { writeln!(f, "   - {}", path)?; }
+        for path in &self.updated { writeln!(f, "   ~ {}", path)?; }
+        for path in &self.tree_added { writeln!(f, "   tree + {}", path)?; }
+        for path in &self.tree_removed { writeln!(f, "   tree - {}", path)?; }
+        Ok(())
+    }
+}
+
 pub struct FileSystem {
     real_path: String,  // the real path of the file system
     root: FSNode,
     current: FSNode,
     side_effects: bool,  // enable / disable side effects on the file system
     db_connection: Option<Arc<Mutex<Connection>>>,
+    owner_id: Option<i64>,  // user whose tree this is, the METADATA rows are scoped by it
 }
 
 impl FileSystem {
@@ -219,6 +297,7 @@ impl FileSystem {
             current: root,
             side_effects: false,
             db_connection: None,
+            owner_id: None,
         }
     }
 
@@ -227,6 +306,16 @@ impl FileSystem {
         self.db_connection = Some(connection);
     }
 
+    // the user whose tree this is. Without an owner every requesting user is treated as the owner
+    pub fn set_owner(&mut self, owner_id: i64) {
+        self.owner_id = Some(owner_id);
+    }
+
+    // user_id of the METADATA rows of this tree, the requesting user is only used for the permission checks
+    fn namespace_id(&self, user_id: i64) -> i64 {
+        self.owner_id.unwrap_or(user_id)
+    }
+
     // function to format permissions in the unix style
     fn format_permissions(user_perms: u16, group_perms: u16, others_perms: u16, is_directory: bool) -> u16 {
         let mut result = String::new();
@@ -234,12 +323,12 @@ impl FileSystem {
         (user_perms << 6) | (group_perms << 3) | others_perms  
     }
 
+    // timestamps are sent as RFC3339 in UTC with all the stored precision, the clients format them
     fn format_timestamp(timestamp: &str) -> String {
-        // Parse timestamp RFC3339 e formatta come "Dec  7 14:30"
         if let Ok(datetime) = chrono::DateTime::parse_from_rfc3339(timestamp) {
-            datetime.format("%b %e %H:%M").to_string()
+            datetime.with_timezone(&chrono::Utc).to_rfc3339_opts(chrono::SecondsFormat::AutoSi, true)
         } else {
-            "Jan  1 00:00".to_string()  // Fallback
+            "1970-01-01T00:00:00Z".to_string()  // Fallback
         }
     }
 
@@ -263,7 +352,11 @@ impl FileSystem {
     fn check_dir_write_permission(&self, dir_path: &str, user_id: i64) -> Result<(), String> {
         // Normalizza il path
         let normalized_path = if (dir_path == "/" || dir_path == "") {
-            return Ok(())       // In the root we always have write permissions
+            // In the root only the owner of the tree has write permissions
+            if self.namespace_id(user_id) == user_id {
+                return Ok(())
+            }
+            return Err("Permission denied: no write access to the root of another user".to_string())
         } else {
             dir_path.trim_start_matches('/').trim_end_matches('/').to_string()
             
@@ -271,6 +364,9 @@ impl FileSystem {
 
         println!("🔐 Checking write permission for user {} in directory '{}'", user_id, normalized_path);
 
+        // the directories above have to be traversable
+        self.check_access(&normalized_path, user_id, 0)?;
+
 
         // Verifica che la directory esista nel filesystem virtuale
         if self.find(&normalized_path).is_none() {
@@ -282,39 +378,32 @@ impl FileSystem {
             let conn = db.lock().unwrap();
             
             let mut stmt = conn.prepare(
-                "SELECT user_id, user_permissions, group_permissions, others_permissions, type 
+                "SELECT COALESCE(owner_id, user_id), user_permissions, group_permissions, others_permissions, type, group_id 
                  FROM METADATA WHERE path = ?1 and User_ID = ?2"
             ).map_err(|e| format!("Database error: {}", e))?;
             
-            let result = stmt.query_row(params![normalized_path, user_id], |row| {
+            let result = stmt.query_row(params![normalized_path, self.namespace_id(user_id)], |row| {
                 let owner_id: i64 = row.get(0)?;
                 let user_perms: u32 = row.get(1)?;
                 let group_perms: u32 = row.get(2)?;
                 let others_perms: u32 = row.get(3)?;
                 let file_type: i32 = row.get(4)?;
+                let group_id: Option<i64> = row.get(5)?;
                 
-                Ok((owner_id, user_perms, group_perms, others_perms, file_type))
+                Ok((owner_id, user_perms, group_perms, others_perms, file_type, group_id))
             });
 
             match result {
-                Ok((owner_id, user_perms, group_perms, others_perms, file_type)) => {
+                Ok((owner_id, user_perms, group_perms, others_perms, file_type, group_id)) => {
                     // Verifica che sia una directory
                     if file_type != 1 {
                         return Err(format!("Invalid request: '{}' is not a directory", dir_path));
                     }
 
                     // Controlla permessi di scrittura (bit 2 = write permission)
-                    let can_write = if owner_id == user_id {
-                        // L'utente è il proprietario
-                        let owner_can_write = (user_perms & 2) != 0;  // Bit 2 = write (-w-)
-                        println!("   Owner check: user_perms={}, can_write={}", user_perms, owner_can_write);
-                        owner_can_write
-                    } else {
-                        // L'utente NON è il proprietario, usa permessi "others"
-                        let others_can_write = (others_perms & 2) != 0;  // Bit 2 = write (--w)
-                        println!("   Others check: others_perms={}, can_write={}", others_perms, others_can_write);
-                        others_can_write
-                    };
+                    let perms = Self::applicable_permissions(&conn, owner_id, group_id, (user_perms, group_perms, others_perms), user_id);
+                    let can_write = (perms & 2) != 0;  // Bit 2 = write (-w-)
+                    println!("   Permission check: perms={}, can_write={}", perms, can_write);
 
                     if can_write {
                         println!("✅ Write permission granted for user {} in '{}'", user_id, dir_path);
@@ -350,6 +439,9 @@ impl FileSystem {
 
         println!("🔐 Checking read permission for user {} in directory '{}'", user_id, normalized_path);
 
+        // the directories above have to be traversable
+        self.check_access(&normalized_path, user_id, 0)?;
+
         // Verifica che la directory esista nel filesystem virtuale
         if self.find(&normalized_path).is_none() {
             return Err(format!("Directory '{}' not found", dir_path));
@@ -359,41 +451,34 @@ impl FileSystem {
             let conn = db.lock().unwrap();
             
             let mut stmt = conn.prepare(
-                "SELECT user_id, user_permissions, group_permissions, others_permissions, type 
+                "SELECT COALESCE(owner_id, user_id), user_permissions, group_permissions, others_permissions, type, group_id 
                 FROM METADATA WHERE path = ?1 AND User_ID = ?2"
             ).map_err(|e| format!("Database error: {}", e))?;
             
-            let result = stmt.query_row(params![normalized_path, user_id], |row| {
+            let result = stmt.query_row(params![normalized_path, self.namespace_id(user_id)], |row| {
                 let owner_id: i64 = row.get(0)?;
                 let user_perms: u32 = row.get(1)?;
                 let group_perms: u32 = row.get(2)?;
                 let others_perms: u32 = row.get(3)?;
                 let file_type: i32 = row.get(4)?;
+                let group_id: Option<i64> = row.get(5)?;
                 
-                Ok((owner_id, user_perms, group_perms, others_perms, file_type))
+                Ok((owner_id, user_perms, group_perms, others_perms, file_type, group_id))
             });
 
             match result {
-                Ok((owner_id, user_perms, _group_perms, others_perms, file_type)) => {
+                Ok((owner_id, user_perms, group_perms, others_perms, file_type, group_id)) => {
                     // Verifica che sia una directory
                     if file_type != 1 {
                         return Err(format!("'{}' is not a directory", dir_path));
                     }
 
                     // ✅ CONTROLLA: Permessi di lettura (bit 4) E execute (bit 1) sulla directory
-                    let can_access = if owner_id == user_id {
-                        let owner_can_read = (user_perms & 4) != 0;  // Bit 4 = read (r--)
-                        let owner_can_execute = (user_perms & 1) != 0;  // Bit 1 = execute (--x)
-                        println!("   Owner check: user_perms={}, can_read={}, can_execute={}", 
-                                user_perms, owner_can_read, owner_can_execute);
-                        owner_can_read && owner_can_execute
-                    } else {
-                        let others_can_read = (others_perms & 4) != 0;  // Bit 4 = read (r--)
-                        let others_can_execute = (others_perms & 1) != 0;  // Bit 1 = execute (--x)
-                        println!("   Others check: others_perms={}, can_read={}, can_execute={}", 
-                                others_perms, others_can_read, others_can_execute);
-                        others_can_read && others_can_execute
-                    };
+                    let perms = Self::applicable_permissions(&conn, owner_id, group_id, (user_perms, group_perms, others_perms), user_id);
+                    let can_read = (perms & 4) != 0;  // Bit 4 = read (r--)
+                    let can_execute = (perms & 1) != 0;  // Bit 1 = execute (--x)
+                    println!("   Permission check: perms={}, can_read={}, can_execute={}", perms, can_read, can_execute);
+                    let can_access = can_read && can_execute;
 
                     if can_access {
                         println!("✅ Read permission granted for user {} in '{}'", user_id, dir_path);
@@ -419,6 +504,98 @@ impl FileSystem {
 
 
 
+    // the permission bits (rwx) that apply to a user: the owner ones, the group ones if the user
+    // is a member of the group of the item, the others ones for everybody else
+    fn applicable_permissions(conn: &Connection, owner_id: i64, group_id: Option<i64>, perms: (u32, u32, u32), user_id: i64) -> u32 {
+        let (user_perms, group_perms, others_perms) = perms;
+        if owner_id == user_id {
+            user_perms
+        } else if group_id.map(|g| Self::is_group_member(conn, g, user_id)).unwrap_or(false) {
+            group_perms
+        } else {
+            others_perms
+        }
+    }
+
+    // permission bits that apply to the user on a single item, None if the item has no metadata
+    fn item_permissions(&self, conn: &Connection, path: &str, user_id: i64) -> Result<Option<u32>, String> {
+        if path.is_empty() {
+            // the root has no row: the owner of the tree can do everything, the others can only look
+            return Ok(Some(if self.namespace_id(user_id) == user_id { 7 } else { 5 }));
+        }
+        let row = conn.query_row(
+            "SELECT COALESCE(owner_id, user_id), user_permissions, group_permissions, others_permissions, group_id
+             FROM METADATA WHERE path = ?1 AND user_id = ?2",
+            params![path, self.namespace_id(user_id)],
+            |row| Ok((row.get::<_, i64>(0)?, row.get::<_, u32>(1)?, row.get::<_, u32>(2)?, row.get::<_, u32>(3)?, row.get::<_, Option<i64>>(4)?)),
+        ).optional().map_err(|e| format!("Database error: {}", e))?;
+        Ok(row.map(|(owner_id, user_perms, group_perms, others_perms, group_id)| {
+            Self::applicable_permissions(conn, owner_id, group_id, (user_perms, group_perms, others_perms), user_id)
+        }))
+    }
+
+    // check that the user can reach the item (execute on every directory above it)
+    // and has the `mask` bits (4 read, 2 write, 1 execute) on the item itself.
+    // For a link the bits are checked on its target
+    pub fn check_access(&self, path: &str, user_id: i64, mask: u32) -> Result<(), String> {
+        let db = match self.db_connection {
+            Some(ref db) => db.clone(),
+            None => return Ok(()),
+        };
+        let normalized_path = path.trim_start_matches('/').trim_end_matches('/').to_string();
+        let parts = FileSystem::split_path(&normalized_path);
+
+        {
+            let conn = db.lock().unwrap();
+            for i in 0..=parts.len() {
+                let current = parts[..i].join("/");
+                let required = if i == parts.len() { mask } else { 1 };
+                let perms = self.item_permissions(&conn, &current, user_id)?
+                    .ok_or(format!("Item {} not found", path))?;
+                if perms & required != required {
+                    return Err(format!("Permission denied: no access to '{}'", path));
+                }
+            }
+        }
+
+        let node = self.find(&format!("/{}", normalized_path));
+        let link = node.and_then(|n| {
+            let lock = n.lock().unwrap();
+            match &*lock {
+                FSItem::SymLink(link) => Some((lock.abs_path(), link.target.clone())),
+                _ => None,
+            }
+        });
+        if let Some((link_dir, target)) = link {
+            let target_node = self.follow_target(&link_dir, &target, 1)
+                .ok_or(format!("Item {} not found", path))?;
+            let target_path = Self::node_db_path(&target_node);
+            if target_path != normalized_path {
+                return self.check_access(&target_path, user_id, mask);
+            }
+        }
+        Ok(())
+    }
+
+    fn is_group_member(conn: &Connection, group_id: i64, user_id: i64) -> bool {
+        conn.query_row(
+            "SELECT COUNT(*) FROM GROUP_MEMBER WHERE Group_ID = ?1 AND User_ID = ?2",
+            params![group_id, user_id],
+            |row| row.get::<_, i64>(0),
+        ).map(|count| count > 0).unwrap_or(false)
+    }
+
+    // new items belong to the group of the directory that contains them
+    fn parent_group_id(conn: &Connection, path: &str, user_id: i64) -> Option<i64> {
+        let normalized_path = path.trim_start_matches('/').trim_end_matches('/');
+        let parent = normalized_path.rsplit_once('/').map(|(p, _)| p)?;
+        conn.query_row(
+            "SELECT group_id FROM METADATA WHERE path = ?1 AND user_id = ?2",
+            params![parent, user_id],
+            |row| row.get::<_, Option<i64>>(0),
+        ).ok().flatten()
+    }
+
     pub fn from_file_system(base_path: &str) -> Self {
         
         let mut fs = FileSystem::new();
@@ -449,8 +626,20 @@ impl FileSystem {
             };
            
             let name = entry_path.file_name().unwrap().to_str().unwrap();
+            if Self::is_upload_temp(name) {
+                continue;
+            }
             
-            if entry_path.is_dir() {
+            // is_dir / is_file follow the links, so they are checked first
+            if entry.path_is_symlink() {
+                // a link that can't be read or added is skipped, its target may not exist
+                let result = fs::read_link(&entry_path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|target| fs.make_link(&head, name, &target.to_string_lossy()));
+                if let Err(e) = result {
+                    println!("Warning: link {} skipped: {}", entry_path.display(), e);
+                }
+            } else if entry_path.is_dir() {
                 fs.make_dir(&head, name).unwrap();
             } else if entry_path.is_file() {
                 fs.make_file(&head, name).unwrap();
@@ -460,6 +649,172 @@ impl FileSystem {
         fs
     }
 
+    // collect the relative paths of all the items of the in-memory tree
+    fn tree_paths(node: &FSNode, prefix: &str, out: &mut Vec<String>) {
+        let lock = node.lock().unwrap();
+        if let Some(children) = lock.get_children() {
+            for child in children {
+                let name = child.lock().unwrap().name().to_string();
+                let path = if prefix.is_empty() { name } else { format!("{}/{}", prefix, name) };
+                out.push(path.clone());
+                Self::tree_paths(child, &path, out);
+            }
+        }
+    }
+
+    // temporary file of an upload still in progress (see prepare_upload), not an item of the tree
+    fn is_upload_temp(name: &str) -> bool {
+        name.starts_with('.') && name.contains(UPLOAD_SUFFIX)
+    }
+
+    // Align the METADATA rows of a user with what is really on disk.
+    // Items created outside the server get default permissions (755 for directories, 644 for files),
+    // rows of items that no longer exist are pruned and stale sizes / modification times are fixed.
+    // The in-memory tree is aligned to the disk as well.
+    pub fn reconcile(&mut self, user_id: i64) -> Result<ReconcileReport, String> {
+        let db = self.db_connection.clone().ok_or("Database connection not initialized")?;
+        let mut report = ReconcileReport::default();
+
+        // disk: relative path -> (type, size, last_modified), same type codes of METADATA
+        let mut on_disk: Vec<(String, i64, i64, String)> = Vec::new();
+        for entry in WalkDir::new(&self.real_path).min_depth(1).into_iter().filter_map(|e| e.ok()) {
+            let rel_path = match entry.path().strip_prefix(&self.real_path) {
+                Ok(p) => p.to_string_lossy().to_string(),
+                Err(_) => continue,
+            };
+            if Self::is_upload_temp(&entry.file_name().to_string_lossy()) {
+                continue;
+            }
+            // links are not followed, this is the metadata of the link itself
+            let meta = entry.metadata().map_err(|e| e.to_string())?;
+            let modified = meta.modified()
+                .map(|t| chrono::DateTime::<chrono::Utc>::from(t).to_rfc3339())
+                .unwrap_or_else(|_| chrono::Utc::now().to_rfc3339());
+            let (file_type, size) = if entry.path_is_symlink() {
+                (2, meta.len() as i64)
+            } else if meta.is_dir() {
+                (1, 0)
+            } else {
+                (0, meta.len() as i64)
+            };
+            on_disk.push((rel_path, file_type, size, modified));
+        }
+
+        // tree
+        let mut in_tree = Vec::new();
+        Self::tree_paths(&self.root, "", &mut in_tree);
+        let tree_set: HashSet<&str> = in_tree.iter().map(|p| p.as_str()).collect();
+        let disk_set: HashSet<String> = on_disk.iter().map(|(p, _, _, _)| p.clone()).collect();
+
+        // only the tree has to be touched, the items are already on disk
+        let side_effects = self.side_effects;
+        self.side_effects = false;
+        for (path, file_type, _, _) in &on_disk {
+            if tree_set.contains(path.as_str()) {
+                continue;
+            }
+            let rel = Path::new(path);
+            let parent = format!("/{}", rel.parent().and_then(|p| p.to_str()).unwrap_or(""));
+            let name = rel.file_name().and_then(|n| n.to_str()).unwrap_or("");
+            let result = match file_type {
+                1 => self.make_dir(&parent, name),
+                2 => match fs::read_link(Path::new(&self.real_path).join(path)) {
+                    Ok(target) => self.make_link(&parent, name, &target.to_string_lossy()),
+                    Err(e) => Err(e.to_string()),
+                },
+                _ => self.make_file(&parent, name),
+            };
+            if result.is_ok() {
+                report.tree_added.push(path.clone());
+            }
+        }
+        self.side_effects = side_effects;
+
+        // deepest first, so a removed directory does not hide its removed children
+        in_tree.sort_by_key(|p| std::cmp::Reverse(p.len()));
+        for path in in_tree {
+            if disk_set.contains(&path) {
+                continue;
+            }
+            if let Some(node) = self.find(&format!("/{}", path)) {
+                let lock = node.lock().unwrap();
+                let name = lock.name().to_string();
+                if let Some(parent) = lock.parent().upgrade() {
+                    drop(lock);
+                    parent.lock().unwrap().remove(&name);
+                }
+                report.tree_removed.push(path);
+            }
+        }
+
+        // database
+        let mut conn = db.lock().unwrap();
+        let rows: HashMap<String, (i64, String)> = {
+            let mut stmt = conn.prepare(
+                "SELECT path, size, last_modified FROM METADATA WHERE user_id = ?1"
+            ).map_err(|e| format!("Database error: {}", e))?;
+            let rows = stmt.query_map(params![user_id], |row| Ok((row.get(0)?, (row.get(1)?, row.get(2)?))))
+                .map_err(|e| format!("Database error: {}", e))?;
+            rows.collect::<Result<_, _>>().map_err(|e| format!("Database error: {}", e))?
+        };
+
+        let tx = conn.transaction().map_err(|e| format!("Database error: {}", e))?;
+        for (path, file_type, size, modified) in &on_disk {
+            match rows.get(path) {
+                None => {
+                    let permissions: u32 = match file_type {
+                        1 => 0o755,
+                        2 => 0o777,
+                        _ => 0o644,
+                    };
+                    let group_id = Self::parent_group_id(&tx, path, user_id);
+                    tx.execute(
+                        "INSERT INTO METADATA (path, user_id, user_permissions, group_permissions, others_permissions, size, created_at, last_modified, type, group_id, owner_id)
+                         VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?2)",
+                        params![
+                            path,
+                            user_id,
+                            (permissions >> 6) & 0o7,
+                            (permissions >> 3) & 0o7,
+                            permissions & 0o7,
+                            size,
+                            modified,
+                            modified,
+                            file_type,
+                            group_id,
+                        ],
+                    ).map_err(|e| format!("Database error: {}", e))?;
+                    report.inserted.push(path.clone());
+                },
+                Some((db_size, db_modified)) => {
+                    // directories change their mtime for every child, only files are compared
+                    let stale_time = *file_type == 0 && chrono::DateTime::parse_from_rfc3339(db_modified)
+                        .map(|t| t.timestamp() != chrono::DateTime::parse_from_rfc3339(modified).unwrap().timestamp())
+                        .unwrap_or(true);
+                    if db_size != size || stale_time {
+                        tx.execute(
+                            "UPDATE METADATA SET size = ?1, last_modified = ?2, last_changed = ?2 WHERE path = ?3 AND user_id = ?4",
+                            params![size, modified, path, user_id],
+                        ).map_err(|e| format!("Database error: {}", e))?;
+                        report.updated.push(path.clone());
+                    }
+                }
+            }
+        }
+        for path in rows.keys() {
+            if !disk_set.contains(path) {
+                tx.execute(
+                    "DELETE FROM METADATA WHERE path = ?1 AND user_id = ?2",
+                    params![path, user_id],
+                ).map_err(|e| format!("Database error: {}", e))?;
+                report.pruned.push(path.clone());
+            }
+        }
+        tx.commit().map_err(|e| format!("Database error: {}", e))?;
+
+        Ok(report)
+    }
+
     pub fn set_real_path(&mut self, path: &str) {
         self.real_path = path.to_string();
     }
@@ -495,6 +850,11 @@ impl FileSystem {
 
     // find using either absolute or relative path
     pub fn find_full(&self, path: &str, base: Option<&str>) -> Option<FSNode> {
+        self.find_with_depth(path, base, 0)
+    }
+
+    // depth counts the links followed so far, so that a loop of links can't recurse forever
+    fn find_with_depth(&self, path: &str, base: Option<&str>, depth: usize) -> Option<FSNode> {
         let parts = FileSystem::split_path(path);
 
         let mut current = if path.starts_with('/') {
@@ -502,13 +862,23 @@ impl FileSystem {
         } else {
             if let Some(base) = base {
                 // if we can't find the base, return None
-                self.find(base)?
+                self.find_with_depth(base, None, depth)?
             } else {
                 self.current.clone()
             }
         };
 
         for part in parts {
+            // a link in the middle of the path is replaced by its target before going on
+            let link = match current.lock().unwrap().deref() {
+                FSItem::SymLink(link) => Some(link.target.clone()),
+                _ => None,
+            };
+            if let Some(target) = link {
+                let link_dir = current.lock().unwrap().abs_path();
+                current = self.follow_target(&link_dir, &target, depth + 1)?;
+            }
+
             let next_node = match current.lock().unwrap().deref() {
                 FSItem::Directory(d) => {
                     if part == "." {
@@ -539,16 +909,7 @@ impl FileSystem {
                         }
                     }
                 },
-                FSItem::SymLink(link) => {
-                    let path = current.lock().unwrap().abs_path();
-                    let target = self.follow_link(&path, &link);
-                    if let Some(target) = target {
-                        target
-                    } else {
-                        return None;
-                    }
-                }
-                FSItem::File(_) => {
+                _ => {
                     return None;
                 }
             };
@@ -560,19 +921,26 @@ impl FileSystem {
     pub fn follow_link(&self, path: &str, link: &SymLink) -> Option<FSNode> {
 
         // path is the absolute path of the link and it necessary if the link is relative
+        self.follow_target(path, &link.target, 1)
+    }
 
-        let node = self.find_full(&link.target, Some(path));
-        if let Some(node) = node {
-            match node.lock().unwrap().deref() {
-                FSItem::Directory(_) => return Some(node.clone()),
-                FSItem::File(_) => return Some(node.clone()),
-                FSItem::SymLink(ref link) => {
-                    let path = node.lock().unwrap().abs_path();
-                    return self.follow_link(&path, link)
-                },
-            }
-        } else {
-            return None
+    // resolve the target of a link, following the chains of links up to MAX_LINK_DEPTH
+    fn follow_target(&self, link_dir: &str, target: &str, depth: usize) -> Option<FSNode> {
+        if depth > MAX_LINK_DEPTH {
+            return None;
+        }
+
+        let node = self.find_with_depth(target, Some(link_dir), depth)?;
+        let next = match node.lock().unwrap().deref() {
+            FSItem::SymLink(link) => Some(link.target.clone()),
+            _ => None,
+        };
+        match next {
+            Some(next_target) => {
+                let next_dir = node.lock().unwrap().abs_path();
+                self.follow_target(&next_dir, &next_target, depth + 1)
+            },
+            None => Some(node),
         }
     }
 
@@ -618,10 +986,10 @@ impl FileSystem {
             
             if !normalized_path.is_empty() {
                 let mut exists_stmt = conn.prepare(
-                    "SELECT COUNT(*) FROM METADATA WHERE path = ?1 AND type = 1"
+                    "SELECT COUNT(*) FROM METADATA WHERE path = ?1 AND type = 1 AND user_id = ?2"
                 ).map_err(|e| format!("Database error: {}", e))?;
                 
-                let exists = exists_stmt.query_row(params![normalized_path], |row| {
+                let exists = exists_stmt.query_row(params![normalized_path, self.namespace_id(requesting_user_id)], |row| {
                     Ok(row.get::<_, i32>(0)? > 0)
                 }).map_err(|e| format!("Database error: {}", e))?;
                 
@@ -644,10 +1012,12 @@ impl FileSystem {
             
             // query
             let mut stmt = conn.prepare(
-                "SELECT m.path, m.user_id, m.user_permissions, m.group_permissions, m.others_permissions, 
-                        m.size, m.last_modified, u.Username, m.type
+                "SELECT m.path, COALESCE(m.owner_id, m.user_id), m.user_permissions, m.group_permissions, m.others_permissions, 
+                        m.size, m.last_modified, u.Username, m.type, g.Name,
+                        m.created_at, COALESCE(m.last_accessed, m.last_modified), COALESCE(m.last_changed, m.last_modified), COALESCE(m.inode_id, m.file_id)
                 FROM METADATA m 
-                LEFT JOIN USER u ON m.user_id = u.User_ID 
+                LEFT JOIN USER u ON COALESCE(m.owner_id, m.user_id) = u.User_ID 
+                LEFT JOIN GROUPS g ON m.group_id = g.Group_ID
                 WHERE m.path LIKE ?1 
                 AND m.user_id = ?2 
                 ORDER BY m.path"
@@ -660,7 +1030,7 @@ impl FileSystem {
                 format!("{}%", normalized_dir)  // File nelle sottodirectory
             };
             
-            let file_iter = stmt.query_map(params![like_pattern, requesting_user_id], |row| {
+            let file_iter = stmt.query_map(params![like_pattern, self.namespace_id(requesting_user_id)], |row| {
                 let path: String = row.get(0)?;
                 let user_id: i64 = row.get(1)?;
                 let user_perms: u16 = row.get(2)?;
@@ -670,14 +1040,17 @@ impl FileSystem {
                 let last_modified: String = row.get(6)?;
                 let username: Option<String> = row.get(7)?;
                 let file_type: i32 = row.get(8)?;
+                let group_name: Option<String> = row.get(9)?;
+                let times: (String, String, String) = (row.get(10)?, row.get(11)?, row.get(12)?);
+                let file_id: i64 = row.get(13)?;
                 
-                Ok((path, user_id, user_perms, group_perms, others_perms, size, last_modified, username, file_type))
+                Ok((path, user_id, user_perms, group_perms, others_perms, size, last_modified, username, file_type, group_name, times, file_id))
             }).map_err(|e| e.to_string())?;
             
             let mut file_infos = Vec::new();
             
             for file_result in file_iter {
-                let (path, user_id, user_perms, group_perms, others_perms, size, last_modified, username, file_type) = 
+                let (path, user_id, user_perms, group_perms, others_perms, size, last_modified, username, file_type, group_name, (created_at, last_accessed, last_changed), file_id) = 
                     file_result.map_err(|e| e.to_string())?;
                 
                 // Filtra i file che sono direttamente nella directory target
@@ -693,20 +1066,30 @@ impl FileSystem {
                 
                 if should_include {
                     let file_name = path.split('/').last().unwrap_or("").to_string();
-                    let is_directory = file_type == 1;  // 1 = directory, 0 = file
+                    let is_directory = file_type == 1;  // 1 = directory, 0 = file, 2 = symlink
                     
                     let permissions = Self::format_permissions(user_perms, group_perms, others_perms, is_directory);
                     let formatted_time = Self::format_timestamp(&last_modified);
                     let owner = username.unwrap_or_else(|| format!("user{}", user_id));
                     
-                    let file_info = FileInfo::new(
-                        permissions,
-                        owner,
-                        size,
-                        formatted_time,
-                        file_name.clone(),
-                        is_directory,
-                    );
+                    let links = self.link_count(&path, file_type);
+                    let file_info = FileInfo {
+                        is_symlink: file_type == 2,
+                        links,
+                        file_id: Some(file_id),
+                        group: group_name.unwrap_or_else(|| DEFAULT_GROUP_NAME.to_string()),
+                        accessed: Self::format_timestamp(&last_accessed),
+                        changed: Self::format_timestamp(&last_changed),
+                        created: Self::format_timestamp(&created_at),
+                        ..FileInfo::new(
+                            permissions,
+                            owner,
+                            size,
+                            formatted_time,
+                            file_name.clone(),
+                            is_directory,
+                        )
+                    };
                     
                     file_infos.push(file_info);
                 }
@@ -732,19 +1115,25 @@ impl FileSystem {
             return Err(format!("Item '{}' not found in fs tree", item_path));
         }
 
+        // the directories above the item have to be traversable
+        self.check_access(&normalized_path, requesting_user_id, 0)?;
+
         if let Some(ref db) = self.db_connection {
             let conn = db.lock().unwrap();
             
             // Query per ottenere i metadati dell'item specifico
             let mut stmt = conn.prepare(
-                "SELECT m.path, m.user_id, m.user_permissions, m.group_permissions, m.others_permissions, 
-                        m.size, m.last_modified, u.Username, m.type
+                "SELECT m.path, COALESCE(m.owner_id, m.user_id), m.user_permissions, m.group_permissions, m.others_permissions, 
+                        m.size, m.last_modified, u.Username, m.type, m.group_id, g.Name,
+                        m.created_at, COALESCE(m.last_accessed, m.last_modified), COALESCE(m.last_changed, m.last_modified), COALESCE(m.inode_id, m.file_id)
                 FROM METADATA m 
-                LEFT JOIN USER u ON m.user_id = u.User_ID 
-                WHERE m.path = ?1"
+                LEFT JOIN USER u ON COALESCE(m.owner_id, m.user_id) = u.User_ID 
+                LEFT JOIN GROUPS g ON m.group_id = g.Group_ID
+                WHERE m.path = ?1
+                AND m.user_id = ?2"
             ).map_err(|e| format!("Database error: {}", e))?;
-            
-            let result = stmt.query_row(params![normalized_path], |row| {
+            // the paths are relative to the user root, so the same path can exist for several users
+            let result = stmt.query_row(params![normalized_path, self.namespace_id(requesting_user_id)], |row| {
                 let path: String = row.get(0)?;
                 let user_id: i64 = row.get(1)?;
                 let user_perms: u16 = row.get(2)?;
@@ -754,21 +1143,22 @@ impl FileSystem {
                 let last_modified: String = row.get(6)?;
                 let username: Option<String> = row.get(7)?;
                 let file_type: i32 = row.get(8)?;
+                let group_id: Option<i64> = row.get(9)?;
+                let group_name: Option<String> = row.get(10)?;
+                let times: (String, String, String) = (row.get(11)?, row.get(12)?, row.get(13)?);
+                let file_id: i64 = row.get(14)?;
                 
-                Ok((path, user_id, user_perms, group_perms, others_perms, size, last_modified, username, file_type))
+                Ok((path, user_id, user_perms, group_perms, others_perms, size, last_modified, username, file_type, group_id, group_name, times, file_id))
             });
 
             match result {
-                Ok((path, user_id, user_perms, group_perms, others_perms, size, last_modified, username, file_type)) => {
+                Ok((path, user_id, user_perms, group_perms, others_perms, size, last_modified, username, file_type, group_id, group_name, (created_at, last_accessed, last_changed), file_id)) => {
                     let is_directory = file_type == 1;
                     let file_name = path.split('/').last().unwrap_or("").to_string();
                     
                     // ✅ CONTROLLA: Permessi di lettura sull'item
-                    let can_read_item = if user_id == requesting_user_id {
-                        (user_perms & 4) != 0  // Owner read permission
-                    } else {
-                        (others_perms & 4) != 0  // Others read permission
-                    };
+                    let perms = Self::applicable_permissions(&conn, user_id, group_id, (user_perms as u32, group_perms as u32, others_perms as u32), requesting_user_id);
+                    let can_read_item = (perms & 4) != 0;
                     
                     // ✅ COMPORTAMENTO: Mostra l'item sempre, ma nascondi dettagli se non leggibile
                     //if can_read_item
@@ -785,14 +1175,24 @@ impl FileSystem {
                     //     ("?".to_string(), 0, "?".to_string(), "??? ?? ??:??".to_string())
                     // };
                     println!("permessions: {}", permissions);
-                    let file_info = FileInfo::new(
-                        permissions,
-                        display_owner,
-                        display_size,
-                        formatted_time,
-                        file_name,
-                        is_directory,
-                    );
+                    let links = self.link_count(&path, file_type);
+                    let file_info = FileInfo {
+                        is_symlink: file_type == 2,
+                        links,
+                        file_id: Some(file_id),
+                        group: group_name.unwrap_or_else(|| DEFAULT_GROUP_NAME.to_string()),
+                        accessed: Self::format_timestamp(&last_accessed),
+                        changed: Self::format_timestamp(&last_changed),
+                        created: Self::format_timestamp(&created_at),
+                        ..FileInfo::new(
+                            permissions,
+                            display_owner,
+                            display_size,
+                            formatted_time,
+                            file_name,
+                            is_directory,
+                        )
+                    };
                     
                     println!("✅ Lookup successful for '{}' by user {}", item_path, requesting_user_id);
                     Ok(file_info)
@@ -862,10 +1262,10 @@ impl FileSystem {
     // this is the version of the make_dir function that also updates the metadat inside the databse (so the one called by main.rs)
     pub fn make_dir_metadata(&mut self, path: &str, name: &str, user_id: i64, permissions: &str) -> Result<(), String> {
         
-        if  path.is_empty() && path.trim()!=("/"){
         // Verifica che l'utente abbia permessi di scrittura nella directory parent
-            self.check_dir_write_permission(path, user_id)?    
-        }
+        // (nella root solo il proprietario dell'albero)
+        self.check_dir_write_permission(path, user_id)?;
+        self.check_quota(user_id, 0, 1)?;
         
         // Permessi da stringa ottale a numero
         let permissions_octal = u32::from_str_radix(permissions, 8)
@@ -899,12 +1299,14 @@ impl FileSystem {
             let group_perms = (permissions_octal >> 3) & 0o7;
             let others_perms = permissions_octal & 0o7;
             
+            let ns = self.namespace_id(user_id);
+            let group_id = Self::parent_group_id(&conn, &full_path, ns);
             let result = conn.execute(
-                "INSERT INTO METADATA (path, user_id, user_permissions, group_permissions, others_permissions, size, created_at, last_modified, type)
-                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                "INSERT INTO METADATA (path, user_id, user_permissions, group_permissions, others_permissions, size, created_at, last_modified, type, group_id, owner_id)
+                VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
                 params![
                     full_path,
-                    user_id,
+                    ns,
                     user_perms,
                     group_perms,
                     others_perms,
@@ -912,6 +1314,8 @@ impl FileSystem {
                     now.clone(),
                     now,
                     1,  // 1 = directory, 0 = file
+                    group_id,
+                    user_id,
                 ],
             );
             
@@ -985,6 +1389,380 @@ impl FileSystem {
         }
     }
 
+    // true if the target, resolved from the directory of the link, stays inside the root.
+    // Absolute targets are refused: on the server they would point outside the user tree
+    fn link_stays_inside(link_dir: &str, target: &str) -> bool {
+        if target.is_empty() || target.starts_with('/') {
+            return false;
+        }
+        let mut parts = FileSystem::split_path(link_dir);
+        for part in FileSystem::split_path(target) {
+            match part {
+                "." => {},
+                ".." => {
+                    if parts.pop().is_none() {
+                        return false;
+                    }
+                },
+                p => parts.push(p),
+            }
+        }
+        true
+    }
+
+    // this is the version of make_link that also checks the target and saves the metadata (so the one called by main.rs)
+    pub fn make_link_metadata(&mut self, path: &str, target: &str, user_id: i64) -> Result<(), String> {
+        let path_buf = PathBuf::from(path);
+        let name = path_buf.file_name().and_then(|n| n.to_str())
+            .ok_or(format!("Invalid request, {} is not a valid link name", path))?
+            .to_string();
+        let parent = path_buf.parent().and_then(|p| p.to_str()).unwrap_or("").to_string();
+
+        let parent_node = self.find(&parent).ok_or(format!("Directory {} not found", parent))?;
+        if !matches!(&*parent_node.lock().unwrap(), FSItem::Directory(_)) {
+            return Err(format!("Invalid request, {} is not a directory", parent));
+        }
+        let parent_path = Self::node_db_path(&parent_node);
+
+        if !Self::link_stays_inside(&parent_path, target) {
+            return Err(format!("Invalid request, link target '{}' is outside the root", target));
+        }
+        if parent_node.lock().unwrap().get_children().unwrap().iter().any(|c| c.lock().unwrap().name() == name) {
+            return Err(format!("Directory or file {} already exists in {}", name, parent));
+        }
+
+        self.check_dir_write_permission(&parent_path, user_id)?;
+        self.check_quota(user_id, 0, 1)?;
+        self.make_link(&format!("/{}", parent_path), &name, target)?;
+
+        let full_path = if parent_path.is_empty() { name } else { format!("{}/{}", parent_path, name) };
+        if let Some(ref db) = self.db_connection {
+            let conn = db.lock().unwrap();
+            let now = chrono::Utc::now().to_rfc3339();
+
+            // links always have rwxrwxrwx, the permissions of the target are the ones that count
+            let ns = self.namespace_id(user_id);
+            let group_id = Self::parent_group_id(&conn, &full_path, ns);
+            let result = conn.execute(
+                "INSERT INTO METADATA (path, user_id, user_permissions, group_permissions, others_permissions, size, created_at, last_modified, type, group_id, owner_id)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
+                params![full_path, ns, 7, 7, 7, target.len() as i64, now.clone(), now, 2, group_id, user_id],
+            );
+            if let Err(e) = result {
+                return Err(format!("Error: {}", e));
+            }
+        }
+
+        println!("✅ Link created: '{}' -> '{}'", full_path, target);
+        Ok(())
+    }
+
+    // target of a symbolic link, as it was written when the link was created
+    pub fn read_link(&self, path: &str) -> Result<String, String> {
+        let node = self.find(path).ok_or(format!("Item {} not found", path))?;
+        let lock = node.lock().unwrap();
+        match &*lock {
+            FSItem::SymLink(link) => Ok(link.target.clone()),
+            _ => Err(format!("Invalid request, {} is not a link", path)),
+        }
+    }
+
+    // add a new name `name` in the directory `path` for the file `source`
+    pub fn make_hard_link(&mut self, path: &str, name: &str, source: &str) -> Result<(), String> {
+        let source_node = self.find(source).ok_or(format!("File {} not found", source))?;
+        if !matches!(&*source_node.lock().unwrap(), FSItem::File(_)) {
+            return Err(format!("Invalid request, {} is not a file", source));
+        }
+
+        if let Some(node) = self.find(path) {
+            if self.side_effects {
+                let real_source = self.make_real_path(source_node.clone());
+                let link_path = PathBuf::from(self.make_real_path(node.clone())).join(name);
+                fs::hard_link(&real_source, &link_path).map_err(|e| e.to_string())?;
+            }
+
+            let size = match &*source_node.lock().unwrap() {
+                FSItem::File(f) => f.size,
+                _ => 0,
+            };
+            let new_file = FSItem::File(File {
+                name: name.to_string(),
+                size,
+                parent: Arc::downgrade(&node),
+            });
+
+            node.lock().unwrap().add(Arc::new(Mutex::new(new_file)));
+            Ok(())
+        } else {
+            Err(format!("Directory {} not found", path))
+        }
+    }
+
+    // this is the version of make_hard_link that also saves the metadata (so the one called by main.rs).
+    // The new name gets the same permissions and size of the source
+    pub fn make_hard_link_metadata(&mut self, source: &str, path: &str, user_id: i64) -> Result<(), String> {
+        let path_buf = PathBuf::from(path);
+        let name = path_buf.file_name().and_then(|n| n.to_str())
+            .ok_or(format!("Invalid request, {} is not a valid link name", path))?
+            .to_string();
+        let parent = path_buf.parent().and_then(|p| p.to_str()).unwrap_or("").to_string();
+
+        let source_node = self.find(source).ok_or(format!("File {} not found", source))?;
+        if !matches!(&*source_node.lock().unwrap(), FSItem::File(_)) {
+            return Err(format!("Invalid request, hard links are allowed only to files ({})", source));
+        }
+        let source_path = Self::node_db_path(&source_node);
+
+        let parent_node = self.find(&parent).ok_or(format!("Directory {} not found", parent))?;
+        if !matches!(&*parent_node.lock().unwrap(), FSItem::Directory(_)) {
+            return Err(format!("Invalid request, {} is not a directory", parent));
+        }
+        let parent_path = Self::node_db_path(&parent_node);
+        if parent_node.lock().unwrap().get_children().unwrap().iter().any(|c| c.lock().unwrap().name() == name) {
+            return Err(format!("Directory or file {} already exists in {}", name, parent));
+        }
+
+        self.check_dir_write_permission(&parent_path, user_id)?;
+        // every name of a hard link is an item and counts its size, like in quota_usage
+        let source_size = self.db_connection.as_ref().and_then(|db| {
+            db.lock().unwrap().query_row(
+                "SELECT size FROM METADATA WHERE path = ?1 AND user_id = ?2",
+                params![source_path, self.namespace_id(user_id)],
+                |row| row.get::<_, i64>(0),
+            ).ok()
+        });
+        self.check_quota(user_id, source_size.unwrap_or(0).max(0) as u64, 1)?;
+        self.make_hard_link(&format!("/{}", parent_path), &name, &format!("/{}", source_path))?;
+
+        let full_path = if parent_path.is_empty() { name } else { format!("{}/{}", parent_path, name) };
+        if let Some(ref db) = self.db_connection {
+            let conn = db.lock().unwrap();
+            let now = chrono::Utc::now().to_rfc3339();
+
+            let result = conn.execute(
+                "INSERT INTO METADATA (path, user_id, user_permissions, group_permissions, others_permissions, size, created_at, last_modified, type, group_id, owner_id, inode_id)
+                 SELECT ?1, user_id, user_permissions, group_permissions, others_permissions, size, ?2, last_modified, 0, group_id, owner_id, COALESCE(inode_id, file_id)
+                 FROM METADATA WHERE path = ?3 AND user_id = ?4",
+                params![full_path, now, source_path, self.namespace_id(user_id)],
+            );
+            if let Err(e) = result {
+                return Err(format!("Error: {}", e));
+            }
+        }
+
+        println!("✅ Hard link created: '{}' -> '{}'", full_path, source_path);
+        Ok(())
+    }
+
+    // change the group of an item (None leaves it without group).
+    // The owner can give the item only to a group it is a member of, an admin to any group
+    pub fn set_group(&self, path: &str, group: Option<&str>, user_id: i64, is_admin: bool) -> Result<(), String> {
+        if self.find(path).is_none() {
+            return Err(format!("Item {} not found", path));
+        }
+        let normalized_path = path.trim_start_matches('/').trim_end_matches('/').to_string();
+        if normalized_path.is_empty() {
+            return Err("Invalid request, the root has no group".to_string());
+        }
+        let paths = self.hard_link_paths(&normalized_path, user_id);
+
+        let db = self.db_connection.clone().ok_or("Database connection not initialized")?;
+        let conn = db.lock().unwrap();
+
+        let ns = self.namespace_id(user_id);
+        let owner_id: i64 = conn.query_row(
+            "SELECT COALESCE(owner_id, user_id) FROM METADATA WHERE path = ?1 AND user_id = ?2",
+            params![normalized_path, ns],
+            |row| row.get(0),
+        ).optional().map_err(|e| format!("Database error: {}", e))?
+            .ok_or(format!("Item {} not found", path))?;
+        if owner_id != user_id && !is_admin {
+            return Err(format!("Permission denied: only the owner can change the group of '{}'", path));
+        }
+
+        let group_id = match group {
+            Some(name) => {
+                let group_id: i64 = conn.query_row(
+                    "SELECT Group_ID FROM GROUPS WHERE Name = ?1",
+                    params![name],
+                    |row| row.get(0),
+                ).optional().map_err(|e| format!("Database error: {}", e))?
+                    .ok_or(format!("Group {} not found", name))?;
+                if !is_admin && !Self::is_group_member(&conn, group_id, user_id) {
+                    return Err(format!("Permission denied: not a member of group '{}'", name));
+                }
+                Some(group_id)
+            },
+            None => None,
+        };
+
+        // the group belongs to the file, so it is the same for all its hard links
+        let now = chrono::Utc::now().to_rfc3339();
+        for p in paths {
+            conn.execute(
+                "UPDATE METADATA SET group_id = ?1, last_changed = ?2 WHERE path = ?3 AND user_id = ?4",
+                params![group_id, now, p, ns],
+            ).map_err(|e| format!("Database error: {}", e))?;
+        }
+
+        println!("👥 Group of '{}' set to {:?}", normalized_path, group);
+        Ok(())
+    }
+
+    // chmod: only the owner of the item (or an admin) can change its permission bits
+    pub fn set_permissions(&self, path: &str, permissions: &str, user_id: i64, is_admin: bool) -> Result<(), String> {
+        let permissions_octal = u32::from_str_radix(permissions, 8)
+            .ok()
+            .filter(|p| *p <= 0o777)
+            .ok_or(format!("Invalid permissions format: {}", permissions))?;
+
+        if self.find(path).is_none() {
+            return Err(format!("Item {} not found", path));
+        }
+        let normalized_path = path.trim_start_matches('/').trim_end_matches('/').to_string();
+        if normalized_path.is_empty() {
+            return Err("Invalid request, the permissions of the root can't be changed".to_string());
+        }
+        let paths = self.hard_link_paths(&normalized_path, user_id);
+
+        let db = self.db_connection.clone().ok_or("Database connection not initialized")?;
+        let conn = db.lock().unwrap();
+
+        let ns = self.namespace_id(user_id);
+        let (owner_id, file_type): (i64, i32) = conn.query_row(
+            "SELECT COALESCE(owner_id, user_id), type FROM METADATA WHERE path = ?1 AND user_id = ?2",
+            params![normalized_path, ns],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).optional().map_err(|e| format!("Database error: {}", e))?
+            .ok_or(format!("Item {} not found", path))?;
+        if owner_id != user_id && !is_admin {
+            return Err(format!("Permission denied: only the owner can change the permissions of '{}'", path));
+        }
+        if file_type == 2 {
+            return Err(format!("Invalid request, '{}' is a symbolic link, its permissions can't be changed", path));
+        }
+
+        // the permissions belong to the file, so they are the same for all its hard links
+        let now = chrono::Utc::now().to_rfc3339();
+        for p in paths {
+            conn.execute(
+                "UPDATE METADATA SET user_permissions = ?1, group_permissions = ?2, others_permissions = ?3, last_changed = ?4 WHERE path = ?5 AND user_id = ?6",
+                params![(permissions_octal >> 6) & 0o7, (permissions_octal >> 3) & 0o7, permissions_octal & 0o7, now, p, ns],
+            ).map_err(|e| format!("Database error: {}", e))?;
+        }
+
+        println!("🔐 Permissions of '{}' set to {:o}", normalized_path, permissions_octal);
+        Ok(())
+    }
+
+    // chown: the items of a tree can be given to another user only by an admin or by the owner of the tree
+    pub fn change_owner(&self, path: &str, new_owner_id: i64, user_id: i64, is_admin: bool) -> Result<(), String> {
+        if self.find(path).is_none() {
+            return Err(format!("Item {} not found", path));
+        }
+        let normalized_path = path.trim_start_matches('/').trim_end_matches('/').to_string();
+        if normalized_path.is_empty() {
+            return Err("Invalid request, the owner of the root can't be changed".to_string());
+        }
+        let ns = self.namespace_id(user_id);
+        if ns != user_id && !is_admin {
+            return Err(format!("Permission denied: only the owner of the tree can change the owner of '{}'", path));
+        }
+        let paths = self.hard_link_paths(&normalized_path, user_id);
+
+        let db = self.db_connection.clone().ok_or("Database connection not initialized")?;
+        let conn = db.lock().unwrap();
+
+        let now = chrono::Utc::now().to_rfc3339();
+        let updated = paths.iter().map(|p| conn.execute(
+            "UPDATE METADATA SET owner_id = ?1, last_changed = ?2 WHERE path = ?3 AND user_id = ?4",
+            params![new_owner_id, now, p, ns],
+        )).sum::<Result<usize, _>>().map_err(|e| format!("Database error: {}", e))?;
+        if updated == 0 {
+            return Err(format!("Item {} not found", path));
+        }
+
+        println!("🔐 Owner of '{}' set to {}", normalized_path, new_owner_id);
+        Ok(())
+    }
+
+    // utimens: the owner (or an admin) and who can write the item can set its access and modification times.
+    // None leaves the time as it is
+    pub fn set_times(&self, path: &str, atime: Option<chrono::DateTime<chrono::Utc>>, mtime: Option<chrono::DateTime<chrono::Utc>>, user_id: i64, is_admin: bool) -> Result<(), String> {
+        let node = self.find(path).ok_or(format!("Item {} not found", path))?;
+        let normalized_path = path.trim_start_matches('/').trim_end_matches('/').to_string();
+        if normalized_path.is_empty() {
+            return Err("Invalid request, the times of the root can't be changed".to_string());
+        }
+        let can_write = self.check_access(path, user_id, 2).is_ok();
+        let paths = self.hard_link_paths(&normalized_path, user_id);
+
+        let db = self.db_connection.clone().ok_or("Database connection not initialized")?;
+        let conn = db.lock().unwrap();
+
+        let ns = self.namespace_id(user_id);
+        let owner_id: i64 = conn.query_row(
+            "SELECT COALESCE(owner_id, user_id) FROM METADATA WHERE path = ?1 AND user_id = ?2",
+            params![normalized_path, ns],
+            |row| row.get(0),
+        ).optional().map_err(|e| format!("Database error: {}", e))?
+            .ok_or(format!("Item {} not found", path))?;
+        if owner_id != user_id && !is_admin && !can_write {
+            return Err(format!("Permission denied: no write access to '{}'", path));
+        }
+
+        // the mtime of the files is also the one on disk, otherwise the reconcile would bring it back
+        let is_file = matches!(&*node.lock().unwrap(), FSItem::File(_));
+        if self.side_effects && is_file {
+            let mut times = fs::FileTimes::new();
+            if let Some(atime) = atime {
+                times = times.set_accessed(atime.into());
+            }
+            if let Some(mtime) = mtime {
+                times = times.set_modified(mtime.into());
+            }
+            OpenOptions::new()
+                .write(true)
+                .open(self.make_real_path(node))
+                .and_then(|f| f.set_times(times))
+                .map_err(|e| e.to_string())?;
+        }
+
+        let now = chrono::Utc::now().to_rfc3339();
+        for p in paths {
+            conn.execute(
+                "UPDATE METADATA SET last_accessed = COALESCE(?1, last_accessed), last_modified = COALESCE(?2, last_modified), last_changed = ?3
+                 WHERE path = ?4 AND user_id = ?5",
+                params![atime.map(|t| t.to_rfc3339()), mtime.map(|t| t.to_rfc3339()), now, p, ns],
+            ).map_err(|e| format!("Database error: {}", e))?;
+        }
+
+        println!("🕒 Times of '{}' set to atime={:?} mtime={:?}", normalized_path, atime, mtime);
+        Ok(())
+    }
+
+    // the access time is updated by the reads like with relatime: only when it is older than the last
+    // modification or than a day, so that reading a file doesn't write on the database every time
+    pub fn mark_accessed(&self, path: &str, user_id: i64) {
+        let normalized_path = path.trim_start_matches('/').trim_end_matches('/').to_string();
+        let paths = self.hard_link_paths(&normalized_path, user_id);
+        if let Some(ref db) = self.db_connection {
+            let conn = db.lock().unwrap();
+            let now = chrono::Utc::now();
+            for p in paths {
+                let result = conn.execute(
+                    "UPDATE METADATA SET last_accessed = ?1
+                     WHERE path = ?2 AND user_id = ?3
+                     AND (last_accessed IS NULL OR last_accessed <= last_modified OR last_accessed < ?4)",
+                    params![now.to_rfc3339(), p, self.namespace_id(user_id), (now - chrono::Duration::days(1)).to_rfc3339()],
+                );
+                if let Err(e) = result {
+                    println!("Warning: Failed to update the access time: {}", e);
+                }
+            }
+        }
+    }
+
     pub fn rename(&self, path: &str, new_name: &str) -> Result<(), String> {
         let node = self.find(path);
         if let Some(n) = node {
@@ -1006,6 +1784,121 @@ impl FileSystem {
         }
     }
 
+    // path of a node relative to the root, as stored in METADATA (e.g. "dir/file.txt")
+    fn node_db_path(node: &FSNode) -> String {
+        let lock = node.lock().unwrap();
+        let parent = lock.abs_path();
+        format!("{}/{}", parent.trim_matches('/'), lock.name()).trim_start_matches('/').to_string()
+    }
+
+    // this is the version of rename that can also move the item to another directory
+    // and that updates the paths inside the database (so the one called by main.rs)
+    pub fn rename_metadata(&mut self, from: &str, to: &str, user_id: i64, no_replace: bool) -> Result<(), String> {
+        let node = self.find(from).ok_or(format!("Item {} not found", from))?;
+        if Arc::ptr_eq(&node, &self.root) {
+            return Err("Invalid request, the root cannot be renamed".to_string());
+        }
+
+        let to_buf = PathBuf::from(to);
+        let new_name = to_buf.file_name().and_then(|n| n.to_str())
+            .filter(|n| *n != "." && *n != "..")
+            .ok_or(format!("Invalid request, {} is not a valid destination", to))?
+            .to_string();
+        let to_parent = to_buf.parent().and_then(|p| p.to_str()).unwrap_or("").to_string();
+
+        let new_parent = self.find(&to_parent).ok_or(format!("Directory {} not found", to_parent))?;
+        if !matches!(&*new_parent.lock().unwrap(), FSItem::Directory(_)) {
+            return Err(format!("Invalid request, {} is not a directory", to_parent));
+        }
+        let old_parent = node.lock().unwrap().parent().upgrade()
+            .ok_or(format!("Item {} not found", from))?;
+
+        // a directory can't be moved inside itself
+        let mut current = Some(new_parent.clone());
+        while let Some(n) = current {
+            if Arc::ptr_eq(&n, &node) {
+                return Err(format!("Invalid request, cannot move {} inside itself", from));
+            }
+            current = n.lock().unwrap().parent().upgrade();
+        }
+
+        let old_path = Self::node_db_path(&node);
+        let old_parent_path = Self::node_db_path(&old_parent);
+        let new_parent_path = Self::node_db_path(&new_parent);
+        let new_path = if new_parent_path.is_empty() { new_name.clone() } else { format!("{}/{}", new_parent_path, new_name) };
+        if old_path == new_path {
+            return Ok(());
+        }
+
+        // removing from the source and adding to the destination both need write permission
+        self.check_dir_write_permission(&old_parent_path, user_id)?;
+        self.check_dir_write_permission(&new_parent_path, user_id)?;
+
+        // an existing destination is replaced only if both are files
+        let existing = self.find(&format!("/{}", new_path));
+        if let Some(ref target) = existing {
+            let both_files = matches!(&*node.lock().unwrap(), FSItem::File(_))
+                && matches!(&*target.lock().unwrap(), FSItem::File(_));
+            if no_replace || !both_files {
+                return Err(format!("Directory or file {} already exists", new_path));
+            }
+        }
+
+        let real_from = self.make_real_path(node.clone());
+        let real_to = PathBuf::from(self.make_real_path(new_parent.clone())).join(&new_name);
+        if self.side_effects {
+            fs::rename(&real_from, &real_to).map_err(|e| e.to_string())?;
+        }
+
+        // the item and all its descendants change path in a single transaction
+        let ns = self.namespace_id(user_id);
+        if let Some(ref db) = self.db_connection {
+            let mut conn = db.lock().unwrap();
+            let result = (|| -> SqlResult<()> {
+                let tx = conn.transaction()?;
+                if existing.is_some() {
+                    tx.execute("DELETE FROM METADATA WHERE path = ?1 AND user_id = ?2", params![new_path, ns])?;
+                }
+                // the prefix is compared exactly: LIKE ignores the case and reads '_' and '%' as wildcards
+                tx.execute(
+                    "UPDATE METADATA SET path = ?1 || substr(path, length(?3) + 1)
+                     WHERE user_id = ?2 AND (path = ?3 OR substr(path, 1, length(?3) + 1) = ?3 || '/')",
+                    params![new_path, ns, old_path],
+                )?;
+                // a rename changes the ctime of the moved item, not of its content
+                tx.execute(
+                    "UPDATE METADATA SET last_changed = ?1 WHERE path = ?2 AND user_id = ?3",
+                    params![chrono::Utc::now().to_rfc3339(), new_path, ns],
+                )?;
+                tx.commit()
+            })();
+
+            if let Err(e) = result {
+                // put the item back where it was
+                if self.side_effects {
+                    let _ = fs::rename(&real_to, &real_from);
+                }
+                return Err(format!("Database error: {}", e));
+            }
+        }
+
+        // finally the in-memory tree
+        let old_name = node.lock().unwrap().name().to_string();
+        old_parent.lock().unwrap().remove(&old_name);
+        if existing.is_some() {
+            new_parent.lock().unwrap().remove(&new_name);
+        }
+        {
+            let mut lock = node.lock().unwrap();
+            lock.set_name(&new_name);
+            lock.set_parent(Arc::downgrade(&new_parent));
+        }
+        new_parent.lock().unwrap().add(node);
+
+        println!("✅ Renamed '{}' to '{}'", old_path, new_path);
+        Ok(())
+    }
+
     pub fn delete(&self, path: &str, user_id: i64) -> Result<(), String> {
         let node:  Option<FSNode>  = self.find(path);
         if let Some(n) = node {
@@ -1017,6 +1910,11 @@ impl FileSystem {
             if let Err(e) = self.check_dir_write_permission(parent_dir, user_id) {
                 return Err(e);
             }
+
+            // a directory is removed with everything inside (see check_owns_subtree)
+            if matches!(&*n.lock().unwrap(), FSItem::Directory(_)) {
+                self.check_owns_subtree(path, user_id)?;
+            }
             
             if self.side_effects {
                 let item=n.lock().unwrap();
@@ -1067,10 +1965,35 @@ impl FileSystem {
         self.side_effects = side_effects;
     }
 
+    // the owner of the tree can always remove what is inside it, the other users only what they own
+    fn check_owns_subtree(&self, path: &str, user_id: i64) -> Result<(), String> {
+        if self.namespace_id(user_id) == user_id {
+            return Ok(());
+        }
+        if let Some(ref db) = self.db_connection {
+            let conn = db.lock().unwrap();
+            let normalized_path = path.trim_start_matches('/').trim_end_matches('/');
+            // exact prefix, like in rename_metadata: a LIKE would also match the siblings that differ
+            // in the case or in a character where the path has a '_'
+            let foreign: Option<String> = conn.query_row(
+                "SELECT path FROM METADATA
+                 WHERE user_id = ?1 AND (path = ?2 OR substr(path, 1, length(?2) + 1) = ?2 || '/') AND COALESCE(owner_id, user_id) != ?3
+                 LIMIT 1",
+                params![self.namespace_id(user_id), normalized_path, user_id],
+                |row| row.get(0),
+            ).optional().map_err(|e| format!("Database error: {}", e))?;
+            if let Some(foreign) = foreign {
+                return Err(format!("Permission denied: cannot delete '{}' owned by another user", foreign));
+            }
+        }
+        Ok(())
+    }
+
     fn remove_from_database(&self, item_path: &str, user_id: i64) -> Result<(), String> {
         if let Some(ref db) = self.db_connection {
             let conn = db.lock().unwrap();
             let normalized_path = item_path.trim_start_matches('/');
+            let ns = self.namespace_id(user_id);
             
             println!("🗄️  Removing from database: '{}'", normalized_path);
             
@@ -1079,7 +2002,7 @@ impl FileSystem {
                 "SELECT type FROM METADATA WHERE path = ?1 AND User_ID = ?2"
             ).map_err(|e| format!("Database error: {}", e))?;
             
-            let file_type = stmt.query_row(params![normalized_path, user_id], |row| {
+            let file_type = stmt.query_row(params![normalized_path, ns], |row| {
                 Ok(row.get::<_, i32>(0)?)
             }).optional().map_err(|e| format!("Database error: {}", e))?;
             
@@ -1090,21 +2013,17 @@ impl FileSystem {
                     
                     // ✅ TROVA: Tutti i file/directory contenuti con controllo proprietà
                     let mut contents_stmt = conn.prepare(
-                        "SELECT path, user_id FROM METADATA 
+                        "SELECT path, COALESCE(owner_id, user_id) FROM METADATA 
                         WHERE ((path = ?1) OR 
-                            (path LIKE ?2)) AND
-                            User_ID = ?3
+                            (?1 = '') OR
+                            (substr(path, 1, length(?1) + 1) = ?1 || '/')) AND
+                            User_ID = ?2
                         ORDER BY LENGTH(path) DESC"  // Prima i file più profondi
                     ).map_err(|e| format!("Database error: {}", e))?;
                     
-                    let recursive_pattern = if normalized_path.is_empty() {
-                        "%".to_string()  // Root directory - tutto
-                    } else {
-                        format!("{}/%", normalized_path)  // Contenuti della directory
-                    };
-                    
+                    // the root contains everything, a directory the paths that start exactly with "<dir>/"
                     let contents_iter = contents_stmt.query_map(
-                        params![normalized_path, recursive_pattern, user_id], 
+                        params![normalized_path, ns], 
                         |row| {
                             let path: String = row.get(0)?;
                             let owner_id: i64 = row.get(1)?;
@@ -1121,7 +2040,7 @@ impl FileSystem {
                         
                         println!("   📋 Found item: '{}', owner={}", content_path, content_owner_id);
                         
-                        if content_owner_id != user_id {
+                        if content_owner_id != user_id && ns != user_id {
                             return Err(format!(
                                 "Permission denied: cannot delete '{}' (owned by user {}, you are user {})", 
                                 content_path, content_owner_id, user_id
@@ -1135,7 +2054,7 @@ impl FileSystem {
                     for path_to_delete in paths_to_delete {
                         let delete_result = conn.execute(
                             "DELETE FROM METADATA WHERE path = ?1 AND user_id = ?2",
-                            params![path_to_delete, user_id],
+                            params![path_to_delete, ns],
                         );
                         
                         match delete_result {
@@ -1155,13 +2074,13 @@ impl FileSystem {
                     println!("✅ Successfully removed directory and all owned contents");
                     Ok(())
                 },
-                Some(0) => {
-                    // ✅ FILE: Elimina solo se appartiene all'utente
+                Some(0) | Some(2) => {
+                    // ✅ FILE (o link): Elimina solo se appartiene all'utente
                     println!("📄 Removing file from database (user {} owns it)", user_id);
                     
                     let delete_result = conn.execute(
                         "DELETE FROM METADATA WHERE path = ?1 AND user_id = ?2",
-                        params![normalized_path, user_id],
+                        params![normalized_path, ns],
                     );
                     
                     match delete_result {
@@ -1186,7 +2105,66 @@ impl FileSystem {
         }
     }
 
-    pub fn write_file(&mut self, path: &str, content: &str, user_id: i64, permissions: &str) -> Result<(), String> {
+    // usage and limits of a user; the space is charged to the tree, not to the owner of the items
+    pub fn quota_usage(conn: &Connection, user_id: i64) -> Result<QuotaUsage, String> {
+        let (used_bytes, used_items): (i64, i64) = conn.query_row(
+            "SELECT COALESCE(SUM(CASE WHEN type = 0 THEN size ELSE 0 END), 0), COUNT(*)
+             FROM METADATA WHERE user_id = ?1",
+            params![user_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).map_err(|e| e.to_string())?;
+        let (max_bytes, max_items): (Option<i64>, Option<i64>) = conn.query_row(
+            "SELECT Quota_Bytes, Quota_Items FROM USER WHERE User_ID = ?1",
+            params![user_id],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).optional().map_err(|e| e.to_string())?.unwrap_or((None, None));
+
+        Ok(QuotaUsage {
+            used_bytes: used_bytes.max(0) as u64,
+            used_items: used_items.max(0) as u64,
+            max_bytes: max_bytes.map(|m| m.max(0) as u64),
+            max_items: max_items.map(|m| m.max(0) as u64),
+        })
+    }
+
+    // fails if `extra_bytes` and `extra_items` more would take the owner over the quota.
+    // Nothing is checked when the usage doesn't grow, so a user over the quota can still free space
+    fn check_quota(&self, user_id: i64, extra_bytes: u64, extra_items: u64) -> Result<(), String> {
+        if extra_bytes == 0 && extra_items == 0 {
+            return Ok(());
+        }
+        let Some(ref db) = self.db_connection else {
+            return Ok(());
+        };
+        // the owner of the tree pays, so a chown can't move the space to someone else
+        let usage = Self::quota_usage(&db.lock().unwrap(), self.namespace_id(user_id))?;
+        if let Some(max_bytes) = usage.max_bytes {
+            if usage.used_bytes + extra_bytes > max_bytes {
+                return Err(format!("Quota exceeded: {} of {} bytes used, {} more requested", usage.used_bytes, max_bytes, extra_bytes));
+            }
+        }
+        if let Some(max_items) = usage.max_items {
+            if usage.used_items + extra_items > max_items {
+                return Err(format!("Quota exceeded: {} of {} items used", usage.used_items, max_items));
+            }
+        }
+        Ok(())
+    }
+
+    // check that an existing file can grow to `new_size`
+    fn check_quota_growth(&self, path: &str, user_id: i64, new_size: u64) -> Result<(), String> {
+        let normalized_path = self.content_db_path(path);
+        let size = self.db_connection.as_ref().and_then(|db| {
+            db.lock().unwrap().query_row(
+                "SELECT size FROM METADATA WHERE path = ?1 AND user_id = ?2",
+                params![normalized_path, self.namespace_id(user_id)],
+                |row| row.get::<_, i64>(0),
+            ).ok()
+        });
+        self.check_quota(user_id, new_size.saturating_sub(size.unwrap_or(0).max(0) as u64), 0)
+    }
+
+    pub fn write_file(&mut self, path: &str, content: &[u8], user_id: i64, permissions: &str) -> Result<(), String> {
         // NParsing permessi da stringa ottale a numero
         let permissions_octal = u32::from_str_radix(permissions, 8)
             .map_err(|_| format!("Invalid permissions format: {}", permissions))?;
@@ -1201,8 +2179,10 @@ impl FileSystem {
             let lock = n.lock().unwrap();
             match &*lock {
                 FSItem::File(_) => {
+                    drop(lock);
+                    self.check_access(path, user_id, 2)?;
+                    self.check_quota_growth(path, user_id, content.len() as u64)?;
                     if self.side_effects {
-                        drop(lock);
                         let real_path = self.make_real_path(n.clone());
                         fs::write(&real_path, content).map_err(|e| e.to_string())?;
                         /*let mut file = OpenOptions::new()
@@ -1214,27 +2194,8 @@ impl FileSystem {
 
                         println!("File already existing {}", path);
 
-                        if let Some(ref db) = self.db_connection {
-                            let conn = db.lock().unwrap();
-                            let now = chrono::Utc::now().to_rfc3339();
-                            
-                            let normalized_path = if path == "/" || path == "" {
-                                "".to_string()
-                            } else {
-                                path.trim_start_matches('/').trim_end_matches('/').to_string()
-                            };
-
-                            println!("UPDATE DB on file '{}'", normalized_path);
-                            let result = conn.execute(
-                                "UPDATE METADATA SET size = ?1, last_modified = ?2 WHERE path = ?3",
-                                params![content_size, now, normalized_path], //real_path?
-                            );
-                            
-                            if let Err(e) = result {
-                                println!("Warning: Failed to update file metadata: {}", e);
-                                // Non blocco l'operazione se l'update metadati fallisce
-                            }
-                        }
+                        // also updates the other names of the file if it has hard links
+                        self.update_size_metadata(path, user_id, content_size as u64);
                     }
                     Ok(())
                 },
@@ -1250,6 +2211,7 @@ impl FileSystem {
             if let Err(e) = self.check_dir_write_permission(path_parent, user_id) {
                 return Err(e);
             }
+            self.check_quota(user_id, content.len() as u64, 1)?;
             
             let parent= self.find(path_parent);
             if let Some(p)=parent{
@@ -1281,12 +2243,14 @@ impl FileSystem {
                                 path.trim_start_matches('/').trim_end_matches('/').to_string()
                             };
 
+                            let ns = self.namespace_id(user_id);
+                            let group_id = Self::parent_group_id(&conn, &normalized_path, ns);
                             let result = conn.execute(
-                                "INSERT INTO METADATA (path, user_id, user_permissions, group_permissions, others_permissions, size, created_at, last_modified, type)
-                                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9)",
+                                "INSERT INTO METADATA (path, user_id, user_permissions, group_permissions, others_permissions, size, created_at, last_modified, type, group_id, owner_id)
+                                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11)",
                                 params![
                                     normalized_path,
-                                    user_id,
+                                    ns,
                                     user_perms,
                                     group_perms,
                                     others_perms,
@@ -1294,6 +2258,8 @@ impl FileSystem {
                                     now.clone(),
                                     now,
                                     0,
+                                    group_id,
+                                    user_id,
                                 ],
                             );
                             
@@ -1317,19 +2283,283 @@ impl FileSystem {
            
     }
     
-    pub fn read_file (&self, path: &str) -> Result<String, String> {
+    // write `content` starting from `offset`, the rest of the file is kept.
+    // The file is created if it does not exist; returns the new size of the file
+    pub fn write_at(&mut self, path: &str, offset: u64, content: &[u8], user_id: i64, permissions: &str) -> Result<u64, String> {
+        // the sizes are i64 in METADATA and for the seek
+        let end = offset.checked_add(content.len() as u64)
+            .filter(|end| *end <= i64::MAX as u64)
+            .ok_or(format!("Invalid offset {}, the file would be too big", offset))?;
+        if self.find(path).is_none() {
+            self.write_file(path, &[], user_id, permissions)?;
+        } else {
+            self.check_access(path, user_id, 2)?;
+        }
+        self.check_quota_growth(path, user_id, end)?;
+
+        let real_path = match self.file_real_path(path)? {
+            Some(real_path) => real_path,
+            None => return Ok(end),
+        };
+
+        let mut file = OpenOptions::new()
+            .write(true)
+            .open(&real_path)
+            .map_err(|e| e.to_string())?;
+        file.seek(SeekFrom::Start(offset)).map_err(|e| e.to_string())?;
+        file.write_all(content).map_err(|e| e.to_string())?;
+        let new_size = file.metadata().map_err(|e| e.to_string())?.len();
+
+        // the size is the one of the whole file, not of the written chunk
+        self.update_size_metadata(path, user_id, new_size);
+
+        Ok(new_size)
+    }
+
+    // resize a file: the part after the new size is dropped, a bigger size adds zeros at the end
+    pub fn truncate(&mut self, path: &str, size: u64, user_id: i64) -> Result<(), String> {
+        if self.find(path).is_none() {
+            return Err(format!("File {} not found", path));
+        }
+        self.check_access(path, user_id, 2)?;
+        self.check_quota_growth(path, user_id, size)?;
+
+        if let Some(real_path) = self.file_real_path(path)? {
+            let file = OpenOptions::new()
+                .write(true)
+                .open(&real_path)
+                .map_err(|e| e.to_string())?;
+            file.set_len(size).map_err(|e| e.to_string())?;
+        }
+
+        self.update_size_metadata(path, user_id, size);
+        Ok(())
+    }
+
+    fn update_size_metadata(&self, path: &str, user_id: i64, size: u64) {
+        let paths = self.hard_link_paths(&self.content_db_path(path), user_id);
+        if let Some(ref db) = self.db_connection {
+            let conn = db.lock().unwrap();
+            let now = chrono::Utc::now().to_rfc3339();
+
+            // every name of the file shares the same content
+            for normalized_path in paths {
+                let result = conn.execute(
+                    "UPDATE METADATA SET size = ?1, last_modified = ?2, last_changed = ?2 WHERE path = ?3 AND user_id = ?4",
+                    params![size as i64, now, normalized_path, self.namespace_id(user_id)],
+                );
+                if let Err(e) = result {
+                    println!("Warning: Failed to update file metadata: {}", e);
+                }
+            }
+        }
+    }
+
+    // number of links of an item: the hard links of a file are counted on disk,
+    // a directory has its own entry, the "." and the ".." of each subdirectory
+    fn link_count(&self, db_path: &str, file_type: i32) -> u32 {
+        match file_type {
+            1 => {
+                let node = if db_path.is_empty() { Some(self.root.clone()) } else { self.find(&format!("/{}", db_path)) };
+                let subdirs = node
+                    .and_then(|n| n.lock().unwrap().get_children().map(|children| {
+                        children.iter().filter(|c| matches!(&*c.lock().unwrap(), FSItem::Directory(_))).count()
+                    }))
+                    .unwrap_or(0);
+                2 + subdirs as u32
+            },
+            0 if self.side_effects => Self::disk_nlink(&PathBuf::from(&self.real_path).join(db_path)),
+            _ => 1,
+        }
+    }
+
+    #[cfg(unix)]
+    fn disk_nlink(real_path: &Path) -> u32 {
+        use std::os::unix::fs::MetadataExt;
+        fs::symlink_metadata(real_path).map(|m| m.nlink() as u32).unwrap_or(1)
+    }
+
+    #[cfg(not(unix))]
+    fn disk_nlink(_real_path: &Path) -> u32 {
+        1
+    }
+
+    // METADATA paths of all the names of a file (the file itself included).
+    // The names linked by the server share the inode_id set by make_hard_link_metadata;
+    // only if the database doesn't know all the links on disk the tree is searched
+    fn hard_link_paths(&self, path: &str, user_id: i64) -> Vec<String> {
+        let normalized_path = path.trim_start_matches('/').trim_end_matches('/').to_string();
+        if !self.side_effects {
+            return vec![normalized_path];
+        }
+        let real_path = PathBuf::from(&self.real_path).join(&normalized_path);
+        let is_file = fs::symlink_metadata(&real_path).map(|m| m.is_file()).unwrap_or(false);
+        let nlink = Self::disk_nlink(&real_path);
+        if !is_file || nlink <= 1 {
+            return vec![normalized_path];
+        }
+
+        let ns = self.namespace_id(user_id);
+        if let Some(ref db) = self.db_connection {
+            let conn = db.lock().unwrap();
+            let group: Vec<String> = conn.prepare(
+                "SELECT path FROM METADATA WHERE user_id = ?2 AND COALESCE(inode_id, file_id) =
+                 (SELECT COALESCE(inode_id, file_id) FROM METADATA WHERE path = ?1 AND user_id = ?2)"
+            )
+            .and_then(|mut stmt| stmt.query_map(params![normalized_path, ns], |row| row.get(0))?.collect())
+            .unwrap_or_default();
+            if group.len() >= nlink as usize {
+                return group;
+            }
+        }
+
+        // links made before the inode_id column or found on disk by reconcile:
+        // they get a shared inode_id, so the next calls don't search the tree again
+        let paths = self.disk_link_paths(&real_path).unwrap_or_else(|| vec![normalized_path]);
+        if let Some(ref db) = self.db_connection {
+            let conn = db.lock().unwrap();
+            let inode = paths.iter()
+                .filter_map(|p| conn.query_row(
+                    "SELECT COALESCE(inode_id, file_id) FROM METADATA WHERE path = ?1 AND user_id = ?2",
+                    params![p, ns],
+                    |row| row.get::<_, i64>(0),
+                ).ok())
+                .min();
+            if let Some(inode) = inode {
+                for p in &paths {
+                    if let Err(e) = conn.execute(
+                        "UPDATE METADATA SET inode_id = ?1 WHERE path = ?2 AND user_id = ?3",
+                        params![inode, p, ns],
+                    ) {
+                        println!("Warning: Failed to update the inode of {}: {}", p, e);
+                    }
+                }
+            }
+        }
+        paths
+    }
+
+    // the items of the tree that are the same file on disk as real_path
+    #[cfg(unix)]
+    fn disk_link_paths(&self, real_path: &Path) -> Option<Vec<String>> {
+        use std::os::unix::fs::MetadataExt;
+        let id = fs::symlink_metadata(real_path).map(|m| (m.dev(), m.ino())).ok()?;
+        let mut all = Vec::new();
+        Self::tree_paths(&self.root, "", &mut all);
+        Some(all.into_iter()
+            .filter(|p| {
+                fs::symlink_metadata(PathBuf::from(&self.real_path).join(p))
+                    .map(|m| m.is_file() && (m.dev(), m.ino()) == id)
+                    .unwrap_or(false)
+            })
+            .collect())
+    }
+
+    #[cfg(not(unix))]
+    fn disk_link_paths(&self, _real_path: &Path) -> Option<Vec<String>> {
+        None
+    }
+
+    // Uploads are written to a temporary file next to the destination and then renamed into place
+    // (see commit_upload), so the filesystem doesn't need to be locked while the body is received.
+    // The checks of write_file are done here, before any byte is transferred;
+    // `expected_size` (the Content-Length, if known) is checked against the quota.
+    pub fn prepare_upload(&self, path: &str, user_id: i64, expected_size: Option<u64>) -> Result<PathBuf, String> {
+        let temp_name = |name: &str| format!(".{}{}{}", name, UPLOAD_SUFFIX, uuid::Uuid::new_v4());
+
+        if self.find(path).is_some() {
+            self.check_access(path, user_id, 2)?;
+            self.check_quota_growth(path, user_id, expected_size.unwrap_or(0))?;
+            return match self.file_real_path(path)? {
+                Some(real_path) => {
+                    let real_path = PathBuf::from(real_path);
+                    let name = real_path.file_name().and_then(|n| n.to_str()).unwrap_or("").to_string();
+                    Ok(real_path.with_file_name(temp_name(&name)))
+                },
+                None => Ok(std::env::temp_dir().join(temp_name("remote-fs"))),
+            };
+        }
+
+        let path_buf = PathBuf::from(path);
+        let path_parent = path_buf.parent().and_then(|p| p.to_str()).unwrap_or("");
+        let file_name = path_buf.file_name().and_then(|n| n.to_str())
+            .ok_or(format!("Invalid request, {} is not a file", path))?;
+
+        // in order to create a file we need to have the write permission on the directory
+        self.check_dir_write_permission(path_parent, user_id)?;
+        self.check_quota(user_id, expected_size.unwrap_or(0), 1)?;
+
+        let parent = self.find(path_parent).ok_or(format!("Directory {} not found", path_parent))?;
+        if !matches!(&*parent.lock().unwrap(), FSItem::Directory(_)) {
+            return Err(format!("Invalid request, {} is not a directory", path_parent));
+        }
+        if self.side_effects {
+            Ok(PathBuf::from(self.make_real_path(parent)).join(temp_name(file_name)))
+        } else {
+            Ok(std::env::temp_dir().join(temp_name(file_name)))
+        }
+    }
+
+    // move a completed upload into place, creating the file and its metadata if needed
+    pub fn commit_upload(&mut self, path: &str, temp_path: &Path, user_id: i64, permissions: &str) -> Result<u64, String> {
+        // the size is checked again, the body may be longer than announced
+        let size = fs::metadata(temp_path).map_err(|e| e.to_string())?.len();
+        if self.find(path).is_none() {
+            self.check_quota(user_id, size, 1)?;
+            self.write_file(path, &[], user_id, permissions)?;
+        } else {
+            self.check_quota_growth(path, user_id, size)?;
+        }
+
+        match self.file_real_path(path)? {
+            // a rename would detach this name from the other hard links, the content is copied in place instead
+            Some(real_path) if Self::disk_nlink(Path::new(&real_path)) > 1 => {
+                fs::copy(temp_path, &real_path).map_err(|e| e.to_string())?;
+                fs::remove_file(temp_path).map_err(|e| e.to_string())?;
+            },
+            Some(real_path) => fs::rename(temp_path, &real_path).map_err(|e| e.to_string())?,
+            None => fs::remove_file(temp_path).map_err(|e| e.to_string())?,
+        }
+        self.update_size_metadata(path, user_id, size);
+
+        Ok(size)
+    }
+
+    // node that holds the content of an item: reading or writing through a link uses its target
+    fn find_content(&self, path: &str) -> Option<FSNode> {
         let node = self.find(path);
-        if let Some(n) = node {
+        let link = node.as_ref().and_then(|n| {
+            let lock = n.lock().unwrap();
+            match &*lock {
+                FSItem::SymLink(link) => Some((lock.abs_path(), link.target.clone())),
+                _ => None,
+            }
+        });
+        match link {
+            Some((link_dir, target)) => self.follow_target(&link_dir, &target, 1),
+            None => node,
+        }
+    }
+
+    // METADATA path of the item that holds the content, so the size of a link's target is the one updated
+    fn content_db_path(&self, path: &str) -> String {
+        match self.find_content(path) {
+            Some(n) => Self::node_db_path(&n),
+            None => path.trim_start_matches('/').trim_end_matches('/').to_string(),
+        }
+    }
+
+    // real path of a file of the tree, None if side effects are disabled
+    pub fn file_real_path(&self, path: &str) -> Result<Option<String>, String> {
+        if let Some(n) = self.find_content(path) {
             let lock = n.lock().unwrap();
             match &*lock {
                 FSItem::File(_) => {
                     if self.side_effects {
                         drop(lock);
-                        let real_path = self.make_real_path(n.clone());
-                        let content = fs::read_to_string(&real_path).map_err(|e| e.to_string())?;
-                        Ok(content)
+                        Ok(Some(self.make_real_path(n.clone())))
                     } else {
-                        Ok(String::new()) // if side effects are disabled, return empty content
+                        Ok(None)
                     }
                 },
                 _ => Err(format!("Invalid request, {} is not a file", path)),
@@ -1339,10 +2569,39 @@ impl FileSystem {
         }
     }
 
+    pub fn read_file (&self, path: &str) -> Result<Vec<u8>, String> {
+        match self.file_real_path(path)? {
+            Some(real_path) => fs::read(&real_path).map_err(|e| e.to_string()),
+            None => Ok(Vec::new()), // if side effects are disabled, return empty content
+        }
+    }
+
+    // size in bytes of the file on disk
+    pub fn file_size(&self, path: &str) -> Result<u64, String> {
+        match self.file_real_path(path)? {
+            Some(real_path) => fs::metadata(&real_path).map(|m| m.len()).map_err(|e| e.to_string()),
+            None => Ok(0),
+        }
+    }
+
+    // read at most `length` bytes starting from `offset`, without loading the rest of the file
+    pub fn read_file_range(&self, path: &str, offset: u64, length: u64) -> Result<Vec<u8>, String> {
+        match self.file_real_path(path)? {
+            Some(real_path) => {
+                let mut file = fs::File::open(&real_path).map_err(|e| e.to_string())?;
+                file.seek(SeekFrom::Start(offset)).map_err(|e| e.to_string())?;
+                let mut content = Vec::new();
+                file.take(length).read_to_end(&mut content).map_err(|e| e.to_string())?;
+                Ok(content)
+            },
+            None => Ok(Vec::new()),
+        }
+    }
+
 }
 
 
 }
 
-pub use crate::filesystem_mod::FileSystem;
+pub use crate::filesystem_mod::{FileInfo, FileSystem, QuotaUsage, ReconcileReport};
 
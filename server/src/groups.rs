@@ -0,0 +1,112 @@
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::Serialize;
+use std::sync::{Arc, Mutex};
+
+// a group with the usernames of its members, as returned by GET /admin/groups
+#[derive(Debug, Serialize)]
+pub struct GroupInfo {
+    pub name: String,
+    pub members: Vec<String>,
+}
+
+// Management of the groups and of their members (GROUPS and GROUP_MEMBER tables).
+// The checks on the group bits of the items are done by the FileSystem.
+pub struct GroupService {
+    conn: Arc<Mutex<Connection>>,
+}
+
+impl GroupService {
+    pub fn new(conn: Arc<Mutex<Connection>>) -> Self {
+        Self { conn }
+    }
+
+    pub fn create_group(&self, name: &str) -> Result<(), String> {
+        let name = name.trim();
+        if name.is_empty() || name.contains('/') {
+            return Err(format!("Invalid group name '{}'", name));
+        }
+
+        let conn = self.conn.lock().unwrap();
+        if Self::group_id(&conn, name)?.is_some() {
+            return Err(format!("Group {} already exists", name));
+        }
+        conn.execute("INSERT INTO GROUPS (Name) VALUES (?1)", params![name])
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        println!("👥 Group created: {}", name);
+        Ok(())
+    }
+
+    // the items of a deleted group are left without group
+    pub fn delete_group(&self, name: &str) -> Result<(), String> {
+        let mut conn = self.conn.lock().unwrap();
+        let group_id = Self::group_id(&conn, name)?.ok_or(format!("Group {} not found", name))?;
+
+        let tx = conn.transaction().map_err(|e| format!("Database error: {}", e))?;
+        tx.execute("UPDATE METADATA SET group_id = NULL WHERE group_id = ?1", params![group_id])
+            .map_err(|e| format!("Database error: {}", e))?;
+        tx.execute("DELETE FROM GROUP_MEMBER WHERE Group_ID = ?1", params![group_id])
+            .map_err(|e| format!("Database error: {}", e))?;
+        tx.execute("DELETE FROM GROUPS WHERE Group_ID = ?1", params![group_id])
+            .map_err(|e| format!("Database error: {}", e))?;
+        tx.commit().map_err(|e| format!("Database error: {}", e))?;
+
+        println!("👥 Group deleted: {}", name);
+        Ok(())
+    }
+
+    // adding a user that is already a member does nothing
+    pub fn add_member(&self, name: &str, user_id: i32) -> Result<(), String> {
+        let conn = self.conn.lock().unwrap();
+        let group_id = Self::group_id(&conn, name)?.ok_or(format!("Group {} not found", name))?;
+        conn.execute(
+            "INSERT OR IGNORE INTO GROUP_MEMBER (Group_ID, User_ID) VALUES (?1, ?2)",
+            params![group_id, user_id],
+        ).map_err(|e| format!("Database error: {}", e))?;
+        Ok(())
+    }
+
+    pub fn remove_member(&self, name: &str, user_id: i32) -> Result<(), String> {
+        let conn = self.conn.lock().unwrap();
+        let group_id = Self::group_id(&conn, name)?.ok_or(format!("Group {} not found", name))?;
+        let removed = conn.execute(
+            "DELETE FROM GROUP_MEMBER WHERE Group_ID = ?1 AND User_ID = ?2",
+            params![group_id, user_id],
+        ).map_err(|e| format!("Database error: {}", e))?;
+        if removed == 0 {
+            return Err(format!("User {} not found in group {}", user_id, name));
+        }
+        Ok(())
+    }
+
+    pub fn list_groups(&self) -> Result<Vec<GroupInfo>, String> {
+        let conn = self.conn.lock().unwrap();
+        let mut stmt = conn.prepare(
+            "SELECT g.Name, u.Username
+             FROM GROUPS g
+             LEFT JOIN GROUP_MEMBER gm ON gm.Group_ID = g.Group_ID
+             LEFT JOIN USER u ON u.User_ID = gm.User_ID
+             ORDER BY g.Name, u.Username"
+        ).map_err(|e| format!("Database error: {}", e))?;
+        let rows = stmt.query_map([], |row| Ok((row.get::<_, String>(0)?, row.get::<_, Option<String>>(1)?)))
+            .map_err(|e| format!("Database error: {}", e))?;
+
+        let mut groups: Vec<GroupInfo> = Vec::new();
+        for row in rows {
+            let (name, member) = row.map_err(|e| format!("Database error: {}", e))?;
+            if groups.last().map(|g| g.name != name).unwrap_or(true) {
+                groups.push(GroupInfo { name, members: Vec::new() });
+            }
+            if let Some(member) = member {
+                groups.last_mut().unwrap().members.push(member);
+            }
+        }
+        Ok(groups)
+    }
+
+    fn group_id(conn: &Connection, name: &str) -> Result<Option<i64>, String> {
+        conn.query_row("SELECT Group_ID FROM GROUPS WHERE Name = ?1", params![name], |row| row.get(0))
+            .optional()
+            .map_err(|e| format!("Database error: {}", e))
+    }
+}
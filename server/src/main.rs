@@ -1,55 +1,159 @@
-use server::FileSystem;
 mod auth;
-use auth::{AuthService, LoginRequest, RegisterRequest};
+mod groups;
+mod migrations;
+mod rate_limit;
+mod registry;
+use auth::{AuthService, ChangePasswordRequest, DeleteAccountRequest, LoginRequest, LogoutRequest, QuotaRequest, RefreshRequest, RegisterRequest};
+use groups::GroupService;
+use rate_limit::LoginLimiter;
+use registry::{FileSystemRegistry, UserFileSystem};
+use server::FileInfo;
 
 use rusqlite::{params, Connection, Result as SqlResult};
 
 use std::sync::{Arc, Mutex};
 use std::path::Path as StdPath;
 use axum::{
-    extract::{Path, State, Query},
-    http::{HeaderMap, StatusCode},
+    body::{Body, Bytes},
+    extract::{ConnectInfo, Path, State, Query},
+    http::{header, HeaderMap, StatusCode},
     response::{IntoResponse, Json},
     routing::{get, post, put, delete},
     Router,
 };
 use std::collections::HashMap;
-use std::net::SocketAddr;
+use serde::Deserialize;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Duration;
+use std::io::SeekFrom;
+use futures::StreamExt;
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio_util::io::ReaderStream;
+
+// a user filesystem not used for this long is dropped from memory
+const FS_IDLE_TIMEOUT: Duration = Duration::from_secs(15 * 60);
+
+// virtual directory, at the root of every user, with the trees of all the users (shared/<username>/...)
+const SHARED_DIR: &str = "shared";
+
+// body of POST /symlink and POST /link: the target of a symbolic link is relative to the link,
+// the one of a hard link is the existing file, relative to the user root
+#[derive(Debug, Deserialize)]
+struct LinkRequest {
+    target: String,
+}
+
+// body of POST /rename, the paths are relative to the user root
+#[derive(Debug, Deserialize)]
+struct RenameRequest {
+    from: String,
+    to: String,
+    #[serde(default)]
+    no_replace: bool,   // fail instead of replacing an existing destination
+}
+
+// body of POST /admin/groups
+#[derive(Debug, Deserialize)]
+struct GroupRequest {
+    name: String,
+}
+
+// body of POST /chgrp, a null group leaves the item without group
+#[derive(Debug, Deserialize)]
+struct ChgrpRequest {
+    group: Option<String>,
+}
+
+// body of POST /metadata, the fields left out are not changed
+#[derive(Debug, Deserialize)]
+struct MetadataRequest {
+    permissions: Option<String>,    // octal, e.g. "640"
+    owner: Option<String>,          // username of the new owner
+    atime: Option<chrono::DateTime<chrono::Utc>>,   // RFC3339, access time
+    mtime: Option<chrono::DateTime<chrono::Utc>>,   // RFC3339, modification time
+}
 
 #[derive(Clone)]
 struct AppState {
     auth_service: Arc<AuthService>,
-    filesystem: Arc<Mutex<Option<FileSystem>>>, // condiviso e clonabile
-    connection: Arc< Mutex<Connection>>,
+    groups: Arc<GroupService>,
+    filesystems: Arc<FileSystemRegistry>, // one filesystem per user
+    login_limiter: Arc<LoginLimiter>,     // failed logins per username and address
+    trusted_proxies: Arc<Vec<IpAddr>>,    // from TRUSTED_PROXIES, they pass the address of the client in X-Forwarded-For
 }
 
 #[tokio::main]
 async fn main()-> SqlResult<()> {
     // Crea (o apre) un database chiamato "mio_database.db"
-    let connection  = Arc::new(Mutex::new(Connection::open("database/db.db")?));
+    std::fs::create_dir_all("database").expect("Failed to create the database directory");
+    let mut connection = Connection::open("database/db.db")?;
+
+    // create the schema on the first start and apply the pending upgrades
+    migrations::run_migrations(&mut connection)?;
+    let connection  = Arc::new(Mutex::new(connection));
 
     // creation of the auth service
     let auth_service = Arc::new(AuthService::new( connection.clone()  ));
-    let fs = Arc::new(Mutex::new(None));
+    let groups = Arc::new(GroupService::new(connection.clone()));
+    let filesystems = Arc::new(FileSystemRegistry::new("remote-fs", connection.clone(), FS_IDLE_TIMEOUT));
+
+    // align disk and METADATA of every user before serving requests
+    for (username, user_id) in auth_service.list_users() {
+        if StdPath::new(&format!("remote-fs/{}", username)).exists() {
+            filesystems.get(&username, user_id);
+        }
+    }
+
+    // periodically evict the filesystems of the users that are no longer active
+    let registry = filesystems.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(registry.idle_timeout() / 2);
+        loop {
+            interval.tick().await;
+            registry.evict_idle();
+        }
+    });
 
     let state = AppState {
         auth_service,
-        filesystem: fs,
-        connection,
+        groups,
+        filesystems,
+        login_limiter: Arc::new(LoginLimiter::new()),
+        trusted_proxies: Arc::new(trusted_proxies()),
     };
 
     let app = Router::new()
         // Route di autenticazione (pubbliche)
         .route("/auth/register", post(register))
         .route("/auth/login", post(login))
+        .route("/auth/refresh", post(refresh))
+        .route("/auth/logout", post(logout))
+        .route("/auth/password", post(change_password))
+        .route("/auth/account", delete(delete_account))
         
         // Route del filesystem (protette)
         .route("/list", get(list_dir_with_empty_path))    // Handler che passa path vuoto
         .route("/list/", get(list_dir_with_empty_path))
         .route("/list/*path", get(list_dir))
-        .route("/files/*path", get(read_file).put(write_file).delete(delete_file))
+        .route("/files/*path", get(read_file).put(write_file).patch(patch_file).delete(delete_file))
         .route("/mkdir/*path", post(mkdir))
+        .route("/rename", post(rename_item))
+        .route("/symlink/*path", post(make_symlink))
+        .route("/link/*path", post(make_hard_link))
+        .route("/readlink/*path", get(read_symlink))
         .route("/lookup/*path", get(lookup_item))
+        .route("/chgrp/*path", post(change_group))
+        .route("/metadata/*path", post(update_metadata))
+        .route("/truncate/*path", post(truncate_file))
+        .route("/quota", get(get_quota))
+
+        // Route di amministrazione (solo admin)
+        .route("/admin/users", get(list_users))
+        .route("/admin/users/:username", delete(delete_user))
+        .route("/admin/users/:username/quota", get(get_user_quota).put(set_user_quota))
+        .route("/admin/groups", get(list_groups).post(create_group))
+        .route("/admin/groups/:name", delete(delete_group))
+        .route("/admin/groups/:name/members/:username", put(add_group_member).delete(remove_group_member))
         
         // Stato condiviso
         .with_state(state);
@@ -59,34 +163,61 @@ async fn main()-> SqlResult<()> {
     
     axum::serve(
         tokio::net::TcpListener::bind(addr).await.unwrap(),
-        app.into_make_service(),
+        // the address of the client is used to limit the failed logins
+        app.into_make_service_with_connect_info::<SocketAddr>(),
     )
     .await
     .unwrap();
     Ok(())
 }
 
-// function to create the file system
-fn create_user_filesystem(username: &str, connection: Arc<Mutex<Connection>>) -> Result<FileSystem, String> {
-    let user_path = format!("remote-fs/{}", username);
-    let mut fs = FileSystem::from_file_system(&user_path);
-    fs.set_side_effects(true);
-    fs.set_database(connection);
-    Ok(fs)
-}
-
 fn is_valid_permissions(permissions: &str) -> bool {
     permissions.len() == 3 &&
     permissions.chars().all(|c| c.is_ascii_digit()) &&
     permissions.chars().all(|c| c as u8 >= b'0' && c as u8 <= b'7')
 }
 
+// parse a single "bytes=start-end" range (also "start-" and "-suffix") into inclusive bounds.
+// None if the header can't be used (then it is ignored), Err if the range is outside the file
+fn parse_range(value: &str, total: u64) -> Option<Result<(u64, u64), String>> {
+    let spec = value.trim().strip_prefix("bytes=")?;
+    if spec.contains(',') {
+        return None; // multiple ranges are not supported
+    }
+    let (start, end) = spec.split_once('-')?;
+    let (start, end) = match (start.trim(), end.trim()) {
+        ("", suffix) => {
+            let suffix: u64 = suffix.parse().ok()?;
+            if suffix == 0 {
+                return Some(Err("Range not satisfiable".to_string()));
+            }
+            (total.saturating_sub(suffix), total.saturating_sub(1))
+        },
+        (start, "") => (start.parse().ok()?, total.saturating_sub(1)),
+        (start, end) => {
+            let start: u64 = start.parse().ok()?;
+            let end: u64 = end.parse().ok()?;
+            if end < start {
+                return None;
+            }
+            (start, end.min(total.saturating_sub(1)))
+        },
+    };
+    if start >= total {
+        return Some(Err("Range not satisfiable".to_string()));
+    }
+    Some(Ok((start, end)))
+}
+
 async fn register(
     State(app_state): State<AppState>,
     Json(req): Json<RegisterRequest>,
 ) -> impl IntoResponse {
-    let auth_service = &app_state.auth_service;
-    match auth_service.register(req) {
+    // bcrypt runs on a blocking thread, so the file requests are not stalled
+    let auth_service = app_state.auth_service.clone();
+    let result = tokio::task::spawn_blocking(move || auth_service.register(req)).await
+        .unwrap_or_else(|e| Err(e.to_string()));
+    match result {
         Ok(message) => {
             (StatusCode::CREATED, message).into_response()
         }
@@ -94,8 +225,8 @@ async fn register(
     }
 }
 
-// FUNCTION TO EXTRACT A USER
-fn extract_user_from_headers(headers: &HeaderMap, auth_service: &AuthService) -> Result<(String, i32), String> {
+// token of the Authorization header
+fn bearer_token(headers: &HeaderMap) -> Result<&str, String> {
     let auth_header = headers
         .get("Authorization")
         .and_then(|h| h.to_str().ok());
@@ -106,28 +237,211 @@ fn extract_user_from_headers(headers: &HeaderMap, auth_service: &AuthService) ->
         return Err("Invalid Authorization header format".to_string());
     }
 
-    let token = &header[7..]; 
+    Ok(&header[7..])
+}
+
+// FUNCTION TO EXTRACT A USER
+fn extract_user_from_headers(headers: &HeaderMap, auth_service: &AuthService) -> Result<(String, i32), String> {
+    let token = bearer_token(headers)?;
     auth_service.validate_token(token)  // returns (username, user_id)
 }
 
+// like extract_user_from_headers, but the user must also be an admin
+fn extract_admin_from_headers(headers: &HeaderMap, auth_service: &AuthService) -> Result<(String, i32), (StatusCode, String)> {
+    let (username, user_id) = extract_user_from_headers(headers, auth_service)
+        .map_err(|e| (StatusCode::UNAUTHORIZED, e))?;
+    if !auth_service.is_admin(user_id) {
+        return Err((StatusCode::FORBIDDEN, "Permission denied: admin only".to_string()));
+    }
+    Ok((username, user_id))
+}
+
+// the filesystem a path belongs to and the path inside it.
+// Paths under shared/<owner>/ are in the tree of that user and the access follows the stored permissions,
+// all the other paths are in the tree of the requesting user
+fn resolve_path(app_state: &AppState, username: &str, user_id: i32, path: &str) -> Result<(UserFileSystem, String), (StatusCode, String)> {
+    let trimmed = path.trim_start_matches('/');
+    let Some(rest) = trimmed.strip_prefix(SHARED_DIR) else {
+        return Ok((app_state.filesystems.get(username, user_id), path.to_string()));
+    };
+    if !rest.is_empty() && !rest.starts_with('/') {
+        // only a name that starts with "shared", like "shared_notes.txt"
+        return Ok((app_state.filesystems.get(username, user_id), path.to_string()));
+    }
+
+    let rest = rest.trim_start_matches('/');
+    if rest.is_empty() {
+        return Err((StatusCode::BAD_REQUEST, format!("Invalid request, /{} is a virtual directory", SHARED_DIR)));
+    }
+    let (owner, inner) = rest.split_once('/').unwrap_or((rest, ""));
+    let owner_id = app_state.auth_service.user_id(owner)
+        .ok_or((StatusCode::NOT_FOUND, format!("User {} not found", owner)))?;
+    Ok((app_state.filesystems.get(owner, owner_id), inner.to_string()))
+}
+
+// FileInfo of the virtual directories: shared itself and the root of each user inside it
+fn virtual_dir_info(name: &str, owner: &str) -> FileInfo {
+    FileInfo {
+        links: 2,
+        ..FileInfo::new(0o755, owner.to_string(), 0, chrono::Utc::now().to_rfc3339(), name.to_string(), true)
+    }
+}
+
+// comma separated addresses of the reverse proxies in front of the server, none by default
+fn trusted_proxies() -> Vec<IpAddr> {
+    std::env::var("TRUSTED_PROXIES")
+        .unwrap_or_default()
+        .split(',')
+        .filter(|p| !p.trim().is_empty())
+        .filter_map(|p| match p.trim().parse() {
+            Ok(ip) => Some(ip),
+            Err(_) => {
+                println!("⚠️  Invalid address in TRUSTED_PROXIES: {}", p);
+                None
+            }
+        })
+        .collect()
+}
+
+// address of the client: the one of the connection, or the last one added to X-Forwarded-For
+// when the connection comes from a trusted proxy (the previous ones can be forged by the client)
+fn client_address(app_state: &AppState, addr: SocketAddr, headers: &HeaderMap) -> IpAddr {
+    if !app_state.trusted_proxies.contains(&addr.ip()) {
+        return addr.ip();
+    }
+    headers.get("X-Forwarded-For")
+        .and_then(|h| h.to_str().ok())
+        .and_then(|h| h.rsplit(',').next())
+        .and_then(|ip| ip.trim().parse().ok())
+        .unwrap_or(addr.ip())
+}
+
 async fn login(
     State(app_state): State<AppState>,
+    ConnectInfo(addr): ConnectInfo<SocketAddr>,
+    headers: HeaderMap,
     Json(req): Json<LoginRequest>,
 ) -> impl IntoResponse {
-    let auth_service = &app_state.auth_service;
-    match auth_service.login(req) {
+    let username = req.username.clone();
+    let ip = client_address(&app_state, addr, &headers).to_string();
+
+    // too many failed attempts: the client has to wait before trying again
+    if let Err(wait) = app_state.login_limiter.begin(&username, &ip) {
+        let retry_after = (wait.as_secs() + u64::from(wait.subsec_nanos() > 0)).max(1);
+        println!("🚫 Login of {} from {} throttled for {}s", username, ip, retry_after);
+        return (
+            StatusCode::TOO_MANY_REQUESTS,
+            [(header::RETRY_AFTER, retry_after.to_string())],
+            format!("Too many failed logins, retry in {} seconds", retry_after),
+        ).into_response();
+    }
+
+    // bcrypt runs on a blocking thread, so a burst of logins doesn't stall the file requests
+    let auth_service = app_state.auth_service.clone();
+    let result = tokio::task::spawn_blocking(move || auth_service.login(req)).await
+        .unwrap_or_else(|e| Err(e.to_string()));
+    match result {
         Ok(response) => {
-            if let Ok(new_fs) = create_user_filesystem(&response.username, app_state.connection) {
-                // Aggiorna il filesystem nell'AppState
-                let mut fs = app_state.filesystem.lock().unwrap();
-                *fs = Some(new_fs);
-            }
+            app_state.login_limiter.record_success(&username, &ip);
+            // load the filesystem of the user in advance, the other users are not affected
+            app_state.filesystems.get(&response.username, response.user_id);
             Json(response).into_response()
         },
+        Err(e) if e.contains("Invalid username or password") => {
+            app_state.login_limiter.record_failure(&username, &ip);
+            (StatusCode::UNAUTHORIZED, e).into_response()
+        },
+        Err(e) => {
+            app_state.login_limiter.cancel(&username, &ip);
+            (StatusCode::UNAUTHORIZED, e).into_response()
+        },
+    }
+}
+
+async fn refresh(
+    State(app_state): State<AppState>,
+    Json(req): Json<RefreshRequest>,
+) -> impl IntoResponse {
+    match app_state.auth_service.refresh(req) {
+        Ok(response) => Json(response).into_response(),
+        Err(e) if e.contains("Database") => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
         Err(e) => (StatusCode::UNAUTHORIZED, e).into_response(),
     }
 }
 
+// the body is optional: without a refresh token all the sessions of the user are closed
+async fn logout(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    req: Option<Json<LogoutRequest>>,
+) -> impl IntoResponse {
+    let token = match bearer_token(&headers) {
+        Ok(token) => token,
+        Err(e) => return (StatusCode::UNAUTHORIZED, e).into_response(),
+    };
+    let refresh_token = req.and_then(|Json(r)| r.refresh_token);
+    match app_state.auth_service.logout(token, refresh_token.as_deref()) {
+        Ok(_) => "Logged out successfully".into_response(),
+        Err(e) if e.contains("Database") => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+        Err(e) => (StatusCode::UNAUTHORIZED, e).into_response(),
+    }
+}
+
+async fn change_password(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<ChangePasswordRequest>,
+) -> impl IntoResponse {
+    let auth_service = &app_state.auth_service;
+    let user_id = match extract_user_from_headers(&headers, auth_service) {
+        Ok((_, id)) => id,
+        Err(e) => return (StatusCode::UNAUTHORIZED, e).into_response(),
+    };
+    let auth_service = auth_service.clone();
+    let result = tokio::task::spawn_blocking(move || auth_service.change_password(user_id, req)).await
+        .unwrap_or_else(|e| Err(e.to_string()));
+    match result {
+        Ok(_) => "Password changed successfully".into_response(),
+        Err(e) if e.contains("Invalid") => (StatusCode::BAD_REQUEST, e).into_response(),
+        Err(e) if e.contains("Permission denied") => (StatusCode::FORBIDDEN, e).into_response(),
+        Err(e) if e.contains("not found") => (StatusCode::NOT_FOUND, e).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+// the account and the files of a user; DELETE /auth/account for itself, DELETE /admin/users/<name> for the admins
+fn remove_account(app_state: &AppState, username: &str, purge: bool) -> axum::response::Response {
+    match app_state.auth_service.delete_user(username, purge) {
+        Ok(user_id) => match app_state.filesystems.remove_user(username, user_id, purge) {
+            Ok(_) => "Account deleted successfully".into_response(),
+            Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, format!("Account deleted, but its files were not removed: {}", e)).into_response(),
+        },
+        Err(e) if e.contains("not found") => (StatusCode::NOT_FOUND, e).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+async fn delete_account(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<DeleteAccountRequest>,
+) -> impl IntoResponse {
+    let auth_service = &app_state.auth_service;
+    let (username, user_id) = match extract_user_from_headers(&headers, auth_service) {
+        Ok((user, id)) => (user, id),
+        Err(e) => return (StatusCode::UNAUTHORIZED, e).into_response(),
+    };
+    // the password is asked again, a token alone is not enough to delete the account
+    let checker = auth_service.clone();
+    let password = req.password.clone();
+    let checked = tokio::task::spawn_blocking(move || checker.check_password(user_id, &password)).await
+        .unwrap_or_else(|e| Err(e.to_string()));
+    if let Err(e) = checked {
+        return (StatusCode::FORBIDDEN, e).into_response();
+    }
+    remove_account(&app_state, &username, req.purge)
+}
+
 // handlers
 async fn list_dir_with_empty_path(
     State(state): State<AppState>,
@@ -144,7 +458,7 @@ async fn list_dir(
 
     let auth_service = &app_state.auth_service;
 
-    let (_username, user_id) = match extract_user_from_headers(&headers, &auth_service) {
+    let (username, user_id) = match extract_user_from_headers(&headers, &auth_service) {
         Ok((user, id)) => {
             (user, id)
         },
@@ -153,11 +467,23 @@ async fn list_dir(
         },
     };
 
-    let mut guard = app_state.filesystem.lock().unwrap();
-    let fs = match guard.as_mut() {
-        Some(fs) => fs,
-        None => return (StatusCode::INTERNAL_SERVER_ERROR, "filesystem non inizializzato").into_response(),
+    // the virtual directory with the trees of the other users
+    if path.trim_matches('/') == SHARED_DIR {
+        let mut users = auth_service.list_users();
+        users.sort();
+        let entries: Vec<FileInfo> = users.iter()
+            .filter(|(name, _)| *name != username)
+            .map(|(name, _)| virtual_dir_info(name, name))
+            .collect();
+        return Json(entries).into_response();
+    }
+    let own_root = path.trim_matches('/').is_empty();
+
+    let (fs_handle, path) = match resolve_path(&app_state, &username, user_id, &path) {
+        Ok(resolved) => resolved,
+        Err(e) => return e.into_response(),
     };
+    let fs = fs_handle.lock().unwrap();
 
     let target_path = if path.is_empty() {
         "".to_string()
@@ -167,12 +493,18 @@ async fn list_dir(
 
     // Usa il nuovo metodo che restituisce FileInfo
     match fs.list_contents_with_metadata(&target_path, user_id as i64) {
-        Ok(files_info) => {
+        Ok(mut files_info) => {
+            if own_root {
+                files_info.push(virtual_dir_info(SHARED_DIR, &username));
+            }
             Json(files_info).into_response()
         },
         Err(e) if e.contains("not found") => {
             (StatusCode::NOT_FOUND, e).into_response()
         },
+        Err(e) if e.contains("Permission denied") => {
+            (StatusCode::FORBIDDEN, e).into_response()
+        },
         Err(e) => {
             (StatusCode::INTERNAL_SERVER_ERROR, e).into_response()
         },
@@ -185,24 +517,124 @@ async fn read_file(
     headers: HeaderMap,
 ) -> impl IntoResponse {
     let auth_service = &app_state.auth_service;
-    if let Err(e) = extract_user_from_headers(&headers, &auth_service) {
-        return (StatusCode::UNAUTHORIZED, e).into_response();
-    }
+    let (username, user_id) = match extract_user_from_headers(&headers, auth_service) {
+        Ok((user, id)) => (user, id),
+        Err(e) => return (StatusCode::UNAUTHORIZED, e).into_response(),
+    };
 
-    let mut guard = app_state.filesystem.lock().unwrap();
-    let fs = match guard.as_mut() {
-        Some(fs) => fs,
-        None => return (StatusCode::INTERNAL_SERVER_ERROR, "filesystem non inizializzato").into_response(),
+    // the filesystem is locked only to resolve the file, the content is streamed without the lock
+    let real_path = {
+        let (fs_handle, path) = match resolve_path(&app_state, &username, user_id, &path) {
+            Ok(resolved) => resolved,
+            Err(e) => return e.into_response(),
+        };
+        let mut fs = fs_handle.lock().unwrap();
+
+        fs.change_dir("/").ok();
+        if let Err(e) = fs.check_access(&path, user_id as i64, 4) {
+            let status = if e.contains("not found") { StatusCode::NOT_FOUND } else { StatusCode::FORBIDDEN };
+            return (status, e).into_response();
+        }
+        match fs.file_real_path(&path) {
+            Ok(Some(real_path)) => {
+                fs.mark_accessed(&path, user_id as i64);
+                real_path
+            },
+            Ok(None) => return ([(header::ACCEPT_RANGES, "bytes")], Vec::new()).into_response(),
+            Err(e) if e.contains("not found") => return (StatusCode::NOT_FOUND, e).into_response(),
+            Err(e) if e.contains("Invalid") => return (StatusCode::BAD_REQUEST, e).into_response(),
+            Err(e) if e.contains("Permission denied") => return (StatusCode::FORBIDDEN, e).into_response(),
+            Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+        }
     };
 
-    fs.change_dir("/").ok();
-    match fs.read_file(&path) {
-        Ok(content) => content.into_response(),
-        Err(e) if e.contains("not found") => (StatusCode::NOT_FOUND, e).into_response(),
-        Err(e) if e.contains("Invalid") => (StatusCode::BAD_REQUEST, e).into_response(),
-        Err(e) if e.contains("Permission denied") => (StatusCode::FORBIDDEN, e).into_response(),
-        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    let mut file = match tokio::fs::File::open(&real_path).await {
+        Ok(file) => file,
+        Err(e) => return (StatusCode::NOT_FOUND, format!("File {} not found: {}", path, e)).into_response(),
+    };
+    let meta = match file.metadata().await {
+        Ok(meta) => meta,
+        Err(e) => return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response(),
+    };
+    let total = meta.len();
+    let etag = file_etag(&meta);
+    let last_modified = meta.modified().map(http_date).unwrap_or_default();
+
+    // the copy of the client is still good, the content is not sent again
+    let if_none_match = headers.get(header::IF_NONE_MATCH).and_then(|h| h.to_str().ok());
+    if if_none_match.is_some_and(|tags| tags.split(',').any(|t| t.trim() == etag || t.trim() == "*")) {
+        return (
+            StatusCode::NOT_MODIFIED,
+            [(header::ETAG, etag), (header::LAST_MODIFIED, last_modified)],
+        ).into_response();
     }
+
+    let range = headers.get(header::RANGE).and_then(|h| h.to_str().ok());
+    match range.and_then(|r| parse_range(r, total)) {
+        // no range (or a range not understood): the whole file is sent
+        None => (
+            [
+                (header::ACCEPT_RANGES, "bytes".to_string()),
+                (header::CONTENT_LENGTH, total.to_string()),
+                (header::ETAG, etag),
+                (header::LAST_MODIFIED, last_modified),
+            ],
+            Body::from_stream(ReaderStream::new(file)),
+        ).into_response(),
+        Some(Ok((start, end))) => {
+            if let Err(e) = file.seek(SeekFrom::Start(start)).await {
+                return (StatusCode::INTERNAL_SERVER_ERROR, e.to_string()).into_response();
+            }
+            let length = end - start + 1;
+            (
+                StatusCode::PARTIAL_CONTENT,
+                [
+                    (header::ACCEPT_RANGES, "bytes".to_string()),
+                    (header::CONTENT_RANGE, format!("bytes {}-{}/{}", start, end, total)),
+                    (header::CONTENT_LENGTH, length.to_string()),
+                    (header::ETAG, etag),
+                    (header::LAST_MODIFIED, last_modified),
+                ],
+                Body::from_stream(ReaderStream::new(file.take(length))),
+            ).into_response()
+        },
+        Some(Err(e)) => (
+            StatusCode::RANGE_NOT_SATISFIABLE,
+            [(header::CONTENT_RANGE, format!("bytes */{}", total))],
+            e,
+        ).into_response(),
+    }
+}
+
+// Validator of the content of a file: it changes when the file is written (size or mtime)
+// or replaced by an upload (inode), so a client can revalidate its copy with If-None-Match
+fn file_etag(meta: &std::fs::Metadata) -> String {
+    let mtime = meta.modified().ok()
+        .and_then(|t| t.duration_since(std::time::UNIX_EPOCH).ok())
+        .map(|d| d.as_nanos())
+        .unwrap_or(0);
+    #[cfg(unix)]
+    let ino = std::os::unix::fs::MetadataExt::ino(meta);
+    #[cfg(not(unix))]
+    let ino = 0u64;
+    format!("\"{:x}-{:x}-{:x}\"", ino, meta.len(), mtime)
+}
+
+// date in the format of the HTTP headers, e.g. "Sun, 06 Nov 1994 08:49:37 GMT"
+fn http_date(time: std::time::SystemTime) -> String {
+    chrono::DateTime::<chrono::Utc>::from(time).format("%a, %d %b %Y %H:%M:%S GMT").to_string()
+}
+
+// receive the body chunk by chunk into the temporary file
+async fn receive_upload(body: Body, temp_path: &StdPath) -> Result<(), String> {
+    let mut file = tokio::fs::File::create(temp_path).await.map_err(|e| e.to_string())?;
+    let mut stream = body.into_data_stream();
+    while let Some(chunk) = stream.next().await {
+        let chunk = chunk.map_err(|e| format!("Upload interrupted: {}", e))?;
+        file.write_all(&chunk).await.map_err(|e| e.to_string())?;
+    }
+    file.flush().await.map_err(|e| e.to_string())?;
+    Ok(())
 }
 
 async fn write_file(
@@ -210,11 +642,11 @@ async fn write_file(
     Path(path): Path<String>,
     headers: HeaderMap,
     query: Query<HashMap<String, String>>,
-    body: String,
+    body: Body,
 ) -> impl IntoResponse {
     let auth_service = &app_state.auth_service;
 
-    let (_username, user_id) = match extract_user_from_headers(&headers, &auth_service) {
+    let (username, user_id) = match extract_user_from_headers(&headers, auth_service) {
         Ok((user, id)) => {
             println!("✅ Authenticated user: {} (id: {})", user, id);
             (user, id)
@@ -233,19 +665,136 @@ async fn write_file(
         return (StatusCode::BAD_REQUEST, "Invalid permissions format. Use 3 octal digits (e.g., 644)").into_response();
     }
 
-    let mut guard = app_state.filesystem.lock().unwrap();
-    let fs = match guard.as_mut() {
-        Some(fs) => fs,
-        None => return (StatusCode::INTERNAL_SERVER_ERROR, "filesystem non inizializzato").into_response(),
+    let (fs_handle, path) = match resolve_path(&app_state, &username, user_id, &path) {
+        Ok(resolved) => resolved,
+        Err(e) => return e.into_response(),
     };
 
-    // TODO cahnge user_id
-    fs.change_dir("/").ok();
-    match fs.write_file(&path, &body, user_id as i64, &permissions) {
+    // with exclusive=true the file must not exist yet (O_EXCL): checked again when it is created,
+    // under the lock, so only one of two concurrent creations succeeds
+    let exclusive = query.get("exclusive").is_some_and(|e| e == "true");
+    let already_exists = |fs: &server::FileSystem| -> Result<(), String> {
+        if exclusive && fs.find(&path).is_some() {
+            return Err(format!("File {} already exists", path));
+        }
+        Ok(())
+    };
+
+    // checks before receiving the body
+    let prepared = {
+        let mut fs = fs_handle.lock().unwrap();
+        fs.change_dir("/").ok();
+        // the announced size is checked against the quota before receiving the body
+        let expected_size = headers.get(header::CONTENT_LENGTH)
+            .and_then(|v| v.to_str().ok())
+            .and_then(|v| v.parse::<u64>().ok());
+        already_exists(&fs).and_then(|_| fs.prepare_upload(&path, user_id as i64, expected_size))
+    };
+
+    let result = match prepared {
+        Ok(temp_path) => {
+            // the body is streamed to disk without holding the filesystem lock
+            match receive_upload(body, &temp_path).await {
+                Ok(()) => {
+                    let mut fs = fs_handle.lock().unwrap();
+                    fs.change_dir("/").ok();
+                    already_exists(&fs).and_then(|_| fs.commit_upload(&path, &temp_path, user_id as i64, &permissions))
+                },
+                Err(e) => Err(e),
+            }.inspect_err(|_| {
+                let _ = std::fs::remove_file(&temp_path);
+            })
+        },
+        Err(e) => Err(e),
+    };
+
+    match result {
         Ok(_) => "File written successfully".into_response(),
         Err(e) if e.contains("not found") => (StatusCode::NOT_FOUND, e).into_response(),
         Err(e) if e.contains("Invalid") => (StatusCode::BAD_REQUEST, e).into_response(),
+        Err(e) if e.contains("already exists") => (StatusCode::CONFLICT, e).into_response(),
         Err(e) if e.contains("Permission denied") => (StatusCode::FORBIDDEN, e).into_response(),
+        Err(e) if e.contains("Quota exceeded") => (StatusCode::INSUFFICIENT_STORAGE, e).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+// write the body at the given offset, without replacing the rest of the file
+async fn patch_file(
+    State(app_state): State<AppState>,
+    Path(path): Path<String>,
+    headers: HeaderMap,
+    query: Query<HashMap<String, String>>,
+    body: Bytes,
+) -> impl IntoResponse {
+    let auth_service = &app_state.auth_service;
+
+    let (username, user_id) = match extract_user_from_headers(&headers, auth_service) {
+        Ok((user, id)) => (user, id),
+        Err(e) => return (StatusCode::UNAUTHORIZED, e).into_response(),
+    };
+
+    let offset = match query.get("offset").map(|o| o.parse::<u64>()) {
+        Some(Ok(offset)) => offset,
+        Some(Err(_)) => return (StatusCode::BAD_REQUEST, "Invalid offset, it must be a non negative integer").into_response(),
+        None => 0,
+    };
+
+    // permissions used only if the file has to be created
+    let permissions = query.get("permissions").unwrap_or(&"644".to_string()).clone();
+    if !is_valid_permissions(&permissions) {
+        return (StatusCode::BAD_REQUEST, "Invalid permissions format. Use 3 octal digits (e.g., 644)").into_response();
+    }
+
+    let (fs_handle, path) = match resolve_path(&app_state, &username, user_id, &path) {
+        Ok(resolved) => resolved,
+        Err(e) => return e.into_response(),
+    };
+    let mut fs = fs_handle.lock().unwrap();
+
+    fs.change_dir("/").ok();
+    match fs.write_at(&path, offset, &body, user_id as i64, &permissions) {
+        Ok(size) => Json(serde_json::json!({ "written": body.len(), "size": size })).into_response(),
+        Err(e) if e.contains("not found") => (StatusCode::NOT_FOUND, e).into_response(),
+        Err(e) if e.contains("Invalid") => (StatusCode::BAD_REQUEST, e).into_response(),
+        Err(e) if e.contains("Permission denied") => (StatusCode::FORBIDDEN, e).into_response(),
+        Err(e) if e.contains("Quota exceeded") => (StatusCode::INSUFFICIENT_STORAGE, e).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+async fn truncate_file(
+    State(app_state): State<AppState>,
+    Path(path): Path<String>,
+    headers: HeaderMap,
+    query: Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    let auth_service = &app_state.auth_service;
+
+    let (username, user_id) = match extract_user_from_headers(&headers, auth_service) {
+        Ok((user, id)) => (user, id),
+        Err(e) => return (StatusCode::UNAUTHORIZED, e).into_response(),
+    };
+
+    let size = match query.get("size").map(|s| s.parse::<u64>()) {
+        Some(Ok(size)) => size,
+        _ => return (StatusCode::BAD_REQUEST, "Invalid size, it must be a non negative integer").into_response(),
+    };
+
+    let (fs_handle, path) = match resolve_path(&app_state, &username, user_id, &path) {
+        Ok(resolved) => resolved,
+        Err(e) => return e.into_response(),
+    };
+    let mut fs = fs_handle.lock().unwrap();
+
+    println!("✂️ Truncating '{}' to {} bytes for user {}", path, size, user_id);
+    fs.change_dir("/").ok();
+    match fs.truncate(&path, size, user_id as i64) {
+        Ok(_) => Json(serde_json::json!({ "size": size })).into_response(),
+        Err(e) if e.contains("not found") => (StatusCode::NOT_FOUND, e).into_response(),
+        Err(e) if e.contains("Invalid") => (StatusCode::BAD_REQUEST, e).into_response(),
+        Err(e) if e.contains("Permission denied") => (StatusCode::FORBIDDEN, e).into_response(),
+        Err(e) if e.contains("Quota exceeded") => (StatusCode::INSUFFICIENT_STORAGE, e).into_response(),
         Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
     }
 }
@@ -260,16 +809,16 @@ async fn delete_file(
         return (StatusCode::UNAUTHORIZED, e).into_response();
     }
 
-    let (_username, user_id) = match extract_user_from_headers(&headers, &auth_service) {
+    let (username, user_id) = match extract_user_from_headers(&headers, &auth_service) {
         Ok((user, id)) => (user, id),
         Err(e) => return (StatusCode::UNAUTHORIZED, e).into_response(),
     };
 
-    let mut guard = app_state.filesystem.lock().unwrap();
-    let fs = match guard.as_mut() {
-        Some(fs) => fs,
-        None => return (StatusCode::INTERNAL_SERVER_ERROR, "filesystem non inizializzato").into_response(),
+    let (fs_handle, path) = match resolve_path(&app_state, &username, user_id, &path) {
+        Ok(resolved) => resolved,
+        Err(e) => return e.into_response(),
     };
+    let mut fs = fs_handle.lock().unwrap();
 
     fs.change_dir("/").ok();
     match fs.delete(&path, user_id as i64) {
@@ -290,7 +839,7 @@ async fn mkdir(
     if let Err(e) = extract_user_from_headers(&headers, &auth_service) {
         return (StatusCode::UNAUTHORIZED, e).into_response();
     }
-    let (_username, user_id) = match extract_user_from_headers(&headers, &auth_service) {
+    let (username, user_id) = match extract_user_from_headers(&headers, &auth_service) {
         Ok((user, id)) => (user, id),
         Err(e) => return (StatusCode::UNAUTHORIZED, e).into_response(),
     };
@@ -303,15 +852,16 @@ async fn mkdir(
         return (StatusCode::BAD_REQUEST, "Invalid permissions format. Use 3 octal digits (e.g., 755)").into_response();
     }
 
-    let mut guard = app_state.filesystem.lock().unwrap();
-    let fs = match guard.as_mut() {
-        Some(fs) => fs,
-        None => return (StatusCode::INTERNAL_SERVER_ERROR, "filesystem non inizializzato").into_response(),
+    let (fs_handle, path) = match resolve_path(&app_state, &username, user_id, &path) {
+        Ok(resolved) => resolved,
+        Err(e) => return e.into_response(),
     };
+    let mut fs = fs_handle.lock().unwrap();
 
     fs.change_dir("/").ok();
 
-    let path = StdPath::new(&path);
+    // the client sends the paths of the mount root with a leading slash ("/mkdir//dir")
+    let path = StdPath::new(path.trim_start_matches('/'));
     let old_dir = path.parent().and_then(|p| p.to_str()).unwrap_or("");
     let new_dir = path.file_name().and_then(|f| f.to_str()).unwrap_or("");
     println!("parent: '{}', new dir: '{}'", old_dir, new_dir);
@@ -321,6 +871,142 @@ async fn mkdir(
         Err(e) if e.contains("Invalid") => (StatusCode::BAD_REQUEST, e).into_response(),
         Err(e) if e.contains("already exists") => (StatusCode::CONFLICT, e).into_response(),
         Err(e) if e.contains("Permission denied") => (StatusCode::FORBIDDEN, e).into_response(),
+        Err(e) if e.contains("Quota exceeded") => (StatusCode::INSUFFICIENT_STORAGE, e).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+async fn rename_item(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<RenameRequest>,
+) -> impl IntoResponse {
+    let auth_service = &app_state.auth_service;
+    let (username, user_id) = match extract_user_from_headers(&headers, auth_service) {
+        Ok((user, id)) => (user, id),
+        Err(e) => return (StatusCode::UNAUTHORIZED, e).into_response(),
+    };
+    println!("🔀 Renaming '{}' to '{}' for user {}", req.from, req.to, user_id);
+
+    let (fs_handle, from) = match resolve_path(&app_state, &username, user_id, &req.from) {
+        Ok(resolved) => resolved,
+        Err(e) => return e.into_response(),
+    };
+    let (to_handle, to) = match resolve_path(&app_state, &username, user_id, &req.to) {
+        Ok(resolved) => resolved,
+        Err(e) => return e.into_response(),
+    };
+    if !Arc::ptr_eq(&fs_handle, &to_handle) {
+        return (StatusCode::BAD_REQUEST, "Invalid request, items can't be moved to the tree of another user").into_response();
+    }
+    let mut fs = fs_handle.lock().unwrap();
+
+    fs.change_dir("/").ok();
+    match fs.rename_metadata(&from, &to, user_id as i64, req.no_replace) {
+        Ok(_) => "Item renamed successfully".into_response(),
+        Err(e) if e.contains("not found") => (StatusCode::NOT_FOUND, e).into_response(),
+        Err(e) if e.contains("Invalid") => (StatusCode::BAD_REQUEST, e).into_response(),
+        Err(e) if e.contains("already exists") => (StatusCode::CONFLICT, e).into_response(),
+        Err(e) if e.contains("Permission denied") => (StatusCode::FORBIDDEN, e).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+async fn make_symlink(
+    State(app_state): State<AppState>,
+    Path(path): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<LinkRequest>,
+) -> impl IntoResponse {
+    let auth_service = &app_state.auth_service;
+    let (username, user_id) = match extract_user_from_headers(&headers, auth_service) {
+        Ok((user, id)) => (user, id),
+        Err(e) => return (StatusCode::UNAUTHORIZED, e).into_response(),
+    };
+    println!("🔗 Making link: '{}' -> '{}' for user {}", path, req.target, user_id);
+
+    let (fs_handle, path) = match resolve_path(&app_state, &username, user_id, &path) {
+        Ok(resolved) => resolved,
+        Err(e) => return e.into_response(),
+    };
+    let mut fs = fs_handle.lock().unwrap();
+
+    fs.change_dir("/").ok();
+    match fs.make_link_metadata(&path, &req.target, user_id as i64) {
+        Ok(_) => "Link created successfully".into_response(),
+        Err(e) if e.contains("not found") => (StatusCode::NOT_FOUND, e).into_response(),
+        Err(e) if e.contains("Invalid") => (StatusCode::BAD_REQUEST, e).into_response(),
+        Err(e) if e.contains("already exists") => (StatusCode::CONFLICT, e).into_response(),
+        Err(e) if e.contains("Permission denied") => (StatusCode::FORBIDDEN, e).into_response(),
+        Err(e) if e.contains("Quota exceeded") => (StatusCode::INSUFFICIENT_STORAGE, e).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+async fn make_hard_link(
+    State(app_state): State<AppState>,
+    Path(path): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<LinkRequest>,
+) -> impl IntoResponse {
+    let auth_service = &app_state.auth_service;
+    let (username, user_id) = match extract_user_from_headers(&headers, auth_service) {
+        Ok((user, id)) => (user, id),
+        Err(e) => return (StatusCode::UNAUTHORIZED, e).into_response(),
+    };
+    println!("🔗 Making hard link: '{}' -> '{}' for user {}", path, req.target, user_id);
+
+    let (fs_handle, path) = match resolve_path(&app_state, &username, user_id, &path) {
+        Ok(resolved) => resolved,
+        Err(e) => return e.into_response(),
+    };
+    let (target_handle, target) = match resolve_path(&app_state, &username, user_id, &req.target) {
+        Ok(resolved) => resolved,
+        Err(e) => return e.into_response(),
+    };
+    if !Arc::ptr_eq(&fs_handle, &target_handle) {
+        return (StatusCode::BAD_REQUEST, "Invalid request, hard links can't cross the tree of another user").into_response();
+    }
+    let mut fs = fs_handle.lock().unwrap();
+
+    fs.change_dir("/").ok();
+    match fs.make_hard_link_metadata(&target, &path, user_id as i64) {
+        Ok(_) => "Link created successfully".into_response(),
+        Err(e) if e.contains("not found") => (StatusCode::NOT_FOUND, e).into_response(),
+        Err(e) if e.contains("Invalid") => (StatusCode::BAD_REQUEST, e).into_response(),
+        Err(e) if e.contains("already exists") => (StatusCode::CONFLICT, e).into_response(),
+        Err(e) if e.contains("Permission denied") => (StatusCode::FORBIDDEN, e).into_response(),
+        Err(e) if e.contains("Quota exceeded") => (StatusCode::INSUFFICIENT_STORAGE, e).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+async fn read_symlink(
+    State(app_state): State<AppState>,
+    Path(path): Path<String>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let auth_service = &app_state.auth_service;
+    let (username, user_id) = match extract_user_from_headers(&headers, auth_service) {
+        Ok((user, id)) => (user, id),
+        Err(e) => return (StatusCode::UNAUTHORIZED, e).into_response(),
+    };
+
+    let (fs_handle, path) = match resolve_path(&app_state, &username, user_id, &path) {
+        Ok(resolved) => resolved,
+        Err(e) => return e.into_response(),
+    };
+    let mut fs = fs_handle.lock().unwrap();
+
+    fs.change_dir("/").ok();
+    if let Err(e) = fs.check_access(&path, user_id as i64, 0) {
+        let status = if e.contains("not found") { StatusCode::NOT_FOUND } else { StatusCode::FORBIDDEN };
+        return (status, e).into_response();
+    }
+    match fs.read_link(&path) {
+        Ok(target) => target.into_response(),
+        Err(e) if e.contains("not found") => (StatusCode::NOT_FOUND, e).into_response(),
+        Err(e) if e.contains("Invalid") => (StatusCode::BAD_REQUEST, e).into_response(),
         Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
     }
 }
@@ -332,7 +1018,7 @@ async fn lookup_item(
 ) -> impl IntoResponse {
     let auth_service = &app_state.auth_service;
 
-    let (_username, user_id) = match extract_user_from_headers(&headers, &auth_service) {
+    let (username, user_id) = match extract_user_from_headers(&headers, &auth_service) {
         Ok((user, id)) => {
             println!("✅ Authenticated user: {} (id: {})", user, id);
             (user, id)
@@ -343,11 +1029,25 @@ async fn lookup_item(
         },
     };
 
-    let guard = app_state.filesystem.lock().unwrap();
-    let fs = match guard.as_ref() {
-        Some(fs) => fs,
-        None => return (StatusCode::INTERNAL_SERVER_ERROR, "filesystem non inizializzato").into_response(),
+    // the virtual directories have no metadata: shared and shared/<username>
+    let trimmed = path.trim_matches('/');
+    if trimmed == SHARED_DIR {
+        return Json(virtual_dir_info(SHARED_DIR, &username)).into_response();
+    }
+    if let Some(owner) = trimmed.strip_prefix(SHARED_DIR).and_then(|r| r.strip_prefix('/')) {
+        if !owner.contains('/') {
+            return match auth_service.user_id(owner) {
+                Some(_) => Json(virtual_dir_info(owner, owner)).into_response(),
+                None => (StatusCode::NOT_FOUND, format!("User {} not found", owner)).into_response(),
+            };
+        }
+    }
+
+    let (fs_handle, path) = match resolve_path(&app_state, &username, user_id, &path) {
+        Ok(resolved) => resolved,
+        Err(e) => return e.into_response(),
     };
+    let fs = fs_handle.lock().unwrap();
 
     println!("🔍 Looking up item: '{}' for user {}", path, user_id);
 
@@ -369,4 +1069,238 @@ async fn lookup_item(
             (StatusCode::INTERNAL_SERVER_ERROR, e).into_response()
         }
     }
-}
\ No newline at end of file
+}
+async fn change_group(
+    State(app_state): State<AppState>,
+    Path(path): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<ChgrpRequest>,
+) -> impl IntoResponse {
+    let auth_service = &app_state.auth_service;
+    let (username, user_id) = match extract_user_from_headers(&headers, auth_service) {
+        Ok((user, id)) => (user, id),
+        Err(e) => return (StatusCode::UNAUTHORIZED, e).into_response(),
+    };
+    println!("👥 Changing group of '{}' to {:?} for user {}", path, req.group, user_id);
+
+    let (fs_handle, path) = match resolve_path(&app_state, &username, user_id, &path) {
+        Ok(resolved) => resolved,
+        Err(e) => return e.into_response(),
+    };
+    let mut fs = fs_handle.lock().unwrap();
+
+    fs.change_dir("/").ok();
+    match fs.set_group(&path, req.group.as_deref(), user_id as i64, auth_service.is_admin(user_id)) {
+        Ok(_) => "Group changed successfully".into_response(),
+        Err(e) if e.contains("not found") => (StatusCode::NOT_FOUND, e).into_response(),
+        Err(e) if e.contains("Invalid") => (StatusCode::BAD_REQUEST, e).into_response(),
+        Err(e) if e.contains("Permission denied") => (StatusCode::FORBIDDEN, e).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+// chmod, chown and utimens of an item, answers with the updated attributes
+async fn update_metadata(
+    State(app_state): State<AppState>,
+    Path(path): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<MetadataRequest>,
+) -> impl IntoResponse {
+    let auth_service = &app_state.auth_service;
+    let (username, user_id) = match extract_user_from_headers(&headers, auth_service) {
+        Ok((user, id)) => (user, id),
+        Err(e) => return (StatusCode::UNAUTHORIZED, e).into_response(),
+    };
+    println!("🔐 Updating metadata of '{}' for user {}: {:?}", path, user_id, req);
+
+    let new_owner = match req.owner.as_deref() {
+        Some(owner) => match auth_service.user_id(owner) {
+            Some(id) => Some(id as i64),
+            None => return (StatusCode::NOT_FOUND, format!("User {} not found", owner)).into_response(),
+        },
+        None => None,
+    };
+
+    let (fs_handle, path) = match resolve_path(&app_state, &username, user_id, &path) {
+        Ok(resolved) => resolved,
+        Err(e) => return e.into_response(),
+    };
+    let mut fs = fs_handle.lock().unwrap();
+
+    fs.change_dir("/").ok();
+    let is_admin = auth_service.is_admin(user_id);
+    let result = fs.check_access(&path, user_id as i64, 0)
+        .and_then(|_| match req.permissions.as_deref() {
+            Some(permissions) => fs.set_permissions(&path, permissions, user_id as i64, is_admin),
+            None => Ok(()),
+        })
+        .and_then(|_| match new_owner {
+            Some(owner_id) => fs.change_owner(&path, owner_id, user_id as i64, is_admin),
+            None => Ok(()),
+        })
+        .and_then(|_| match (req.atime, req.mtime) {
+            (None, None) => Ok(()),
+            (atime, mtime) => fs.set_times(&path, atime, mtime, user_id as i64, is_admin),
+        })
+        .and_then(|_| fs.lookup_item(&path, user_id as i64));
+
+    match result {
+        Ok(file_info) => Json(file_info).into_response(),
+        Err(e) if e.contains("not found") => (StatusCode::NOT_FOUND, e).into_response(),
+        Err(e) if e.contains("Invalid") => (StatusCode::BAD_REQUEST, e).into_response(),
+        Err(e) if e.contains("Permission denied") => (StatusCode::FORBIDDEN, e).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+async fn list_users(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if let Err(e) = extract_admin_from_headers(&headers, &app_state.auth_service) {
+        return e.into_response();
+    }
+    Json(app_state.auth_service.list_accounts()).into_response()
+}
+
+async fn delete_user(
+    State(app_state): State<AppState>,
+    Path(username): Path<String>,
+    headers: HeaderMap,
+    query: Query<HashMap<String, String>>,
+) -> impl IntoResponse {
+    if let Err(e) = extract_admin_from_headers(&headers, &app_state.auth_service) {
+        return e.into_response();
+    }
+    let purge = query.get("purge").is_some_and(|p| p == "true");
+    remove_account(&app_state, &username, purge)
+}
+
+// space used by the caller and the limits of the account
+async fn get_quota(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    let (_, user_id) = match extract_user_from_headers(&headers, &app_state.auth_service) {
+        Ok((user, id)) => (user, id),
+        Err(e) => return (StatusCode::UNAUTHORIZED, e).into_response(),
+    };
+    match app_state.auth_service.quota_usage(user_id) {
+        Ok(usage) => Json(usage).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+async fn get_user_quota(
+    State(app_state): State<AppState>,
+    Path(username): Path<String>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if let Err(e) = extract_admin_from_headers(&headers, &app_state.auth_service) {
+        return e.into_response();
+    }
+    let Some(user_id) = app_state.auth_service.user_id(&username) else {
+        return (StatusCode::NOT_FOUND, format!("User {} not found", username)).into_response();
+    };
+    match app_state.auth_service.quota_usage(user_id) {
+        Ok(usage) => Json(usage).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+async fn set_user_quota(
+    State(app_state): State<AppState>,
+    Path(username): Path<String>,
+    headers: HeaderMap,
+    Json(req): Json<QuotaRequest>,
+) -> impl IntoResponse {
+    if let Err(e) = extract_admin_from_headers(&headers, &app_state.auth_service) {
+        return e.into_response();
+    }
+    let auth_service = &app_state.auth_service;
+    match auth_service.set_quota(&username, &req).and_then(|user_id| auth_service.quota_usage(user_id)) {
+        Ok(usage) => Json(usage).into_response(),
+        Err(e) if e.contains("not found") => (StatusCode::NOT_FOUND, e).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+async fn list_groups(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if let Err(e) = extract_admin_from_headers(&headers, &app_state.auth_service) {
+        return e.into_response();
+    }
+    match app_state.groups.list_groups() {
+        Ok(groups) => Json(groups).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+async fn create_group(
+    State(app_state): State<AppState>,
+    headers: HeaderMap,
+    Json(req): Json<GroupRequest>,
+) -> impl IntoResponse {
+    if let Err(e) = extract_admin_from_headers(&headers, &app_state.auth_service) {
+        return e.into_response();
+    }
+    match app_state.groups.create_group(&req.name) {
+        Ok(_) => "Group created successfully".into_response(),
+        Err(e) if e.contains("Invalid") => (StatusCode::BAD_REQUEST, e).into_response(),
+        Err(e) if e.contains("already exists") => (StatusCode::CONFLICT, e).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+async fn delete_group(
+    State(app_state): State<AppState>,
+    Path(name): Path<String>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if let Err(e) = extract_admin_from_headers(&headers, &app_state.auth_service) {
+        return e.into_response();
+    }
+    match app_state.groups.delete_group(&name) {
+        Ok(_) => "Group deleted successfully".into_response(),
+        Err(e) if e.contains("not found") => (StatusCode::NOT_FOUND, e).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+async fn add_group_member(
+    State(app_state): State<AppState>,
+    Path((name, member)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if let Err(e) = extract_admin_from_headers(&headers, &app_state.auth_service) {
+        return e.into_response();
+    }
+    let Some(member_id) = app_state.auth_service.user_id(&member) else {
+        return (StatusCode::NOT_FOUND, format!("User {} not found", member)).into_response();
+    };
+    match app_state.groups.add_member(&name, member_id) {
+        Ok(_) => "Member added successfully".into_response(),
+        Err(e) if e.contains("not found") => (StatusCode::NOT_FOUND, e).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
+
+async fn remove_group_member(
+    State(app_state): State<AppState>,
+    Path((name, member)): Path<(String, String)>,
+    headers: HeaderMap,
+) -> impl IntoResponse {
+    if let Err(e) = extract_admin_from_headers(&headers, &app_state.auth_service) {
+        return e.into_response();
+    }
+    let Some(member_id) = app_state.auth_service.user_id(&member) else {
+        return (StatusCode::NOT_FOUND, format!("User {} not found", member)).into_response();
+    };
+    match app_state.groups.remove_member(&name, member_id) {
+        Ok(_) => "Member removed successfully".into_response(),
+        Err(e) if e.contains("not found") => (StatusCode::NOT_FOUND, e).into_response(),
+        Err(e) => (StatusCode::INTERNAL_SERVER_ERROR, e).into_response(),
+    }
+}
@@ -0,0 +1,232 @@
+use rusqlite::{params, Connection, OptionalExtension, Result as SqlResult};
+
+// A single upgrade of the database schema.
+// Migrations are applied in order of version and each one only once:
+// to change the schema add a new entry at the end of MIGRATIONS, never edit an applied one.
+struct Migration {
+    version: u32,
+    description: &'static str,
+    sql: &'static str,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        version: 1,
+        description: "create USER and METADATA tables",
+        // IF NOT EXISTS so that databases created by hand before the migrations are adopted
+        sql: "
+            CREATE TABLE IF NOT EXISTS USER (
+                User_ID INTEGER PRIMARY KEY AUTOINCREMENT,
+                Username TEXT NOT NULL UNIQUE,
+                Password TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS METADATA (
+                file_id INTEGER PRIMARY KEY AUTOINCREMENT,
+                path TEXT NOT NULL,
+                user_id INTEGER NOT NULL,
+                user_permissions INTEGER NOT NULL,
+                group_permissions INTEGER NOT NULL,
+                others_permissions INTEGER NOT NULL,
+                size INTEGER NOT NULL DEFAULT 0,
+                created_at TEXT NOT NULL,
+                last_modified TEXT NOT NULL,
+                type INTEGER NOT NULL
+            );
+            CREATE INDEX IF NOT EXISTS idx_metadata_user_path ON METADATA (user_id, path);
+        ",
+    },
+    Migration {
+        version: 2,
+        description: "add groups, group owner of the items and admin role",
+        // group_id NULL means that the item has no group, so its group bits apply to nobody
+        sql: "
+            CREATE TABLE GROUPS (
+                Group_ID INTEGER PRIMARY KEY AUTOINCREMENT,
+                Name TEXT NOT NULL UNIQUE
+            );
+            CREATE TABLE GROUP_MEMBER (
+                Group_ID INTEGER NOT NULL REFERENCES GROUPS (Group_ID),
+                User_ID INTEGER NOT NULL REFERENCES USER (User_ID),
+                PRIMARY KEY (Group_ID, User_ID)
+            );
+            ALTER TABLE METADATA ADD COLUMN group_id INTEGER REFERENCES GROUPS (Group_ID);
+            ALTER TABLE USER ADD COLUMN Is_Admin INTEGER NOT NULL DEFAULT 0;
+        ",
+    },
+    Migration {
+        version: 3,
+        description: "separate the owner of an item from the tree it belongs to",
+        // user_id stays the user whose tree contains the item, owner_id is who created it
+        sql: "
+            ALTER TABLE METADATA ADD COLUMN owner_id INTEGER REFERENCES USER (User_ID);
+            UPDATE METADATA SET owner_id = user_id;
+        ",
+    },
+    Migration {
+        version: 4,
+        description: "add access and change times of the items",
+        // last_changed is the ctime: content, permissions, owner, group or name changed
+        sql: "
+            ALTER TABLE METADATA ADD COLUMN last_accessed TEXT;
+            ALTER TABLE METADATA ADD COLUMN last_changed TEXT;
+            UPDATE METADATA SET last_accessed = last_modified, last_changed = last_modified;
+        ",
+    },
+    Migration {
+        version: 5,
+        description: "add refresh tokens and revoked tokens",
+        // Expires_At is a unix timestamp, the expired rows are dropped at the start of the server
+        sql: "
+            CREATE TABLE REFRESH_TOKEN (
+                Token TEXT PRIMARY KEY,
+                User_ID INTEGER NOT NULL REFERENCES USER (User_ID),
+                Expires_At INTEGER NOT NULL
+            );
+            CREATE TABLE REVOKED_TOKEN (
+                Jti TEXT PRIMARY KEY,
+                Expires_At INTEGER NOT NULL
+            );
+        ",
+    },
+    Migration {
+        version: 6,
+        description: "add the storage quotas of the users",
+        // NULL means unlimited, the usage is computed from METADATA
+        sql: "
+            ALTER TABLE USER ADD COLUMN Quota_Bytes INTEGER;
+            ALTER TABLE USER ADD COLUMN Quota_Items INTEGER;
+        ",
+    },
+    Migration {
+        version: 7,
+        description: "add the inode shared by the names of a hard link",
+        // NULL means the item is its own inode (the file_id), a new name of a hard link
+        // gets the inode of its source, so it doesn't change when a name is removed
+        sql: "ALTER TABLE METADATA ADD COLUMN inode_id INTEGER;",
+    },
+];
+
+// create the table used to remember which migrations are already applied
+fn ensure_version_table(conn: &Connection) -> SqlResult<()> {
+    conn.execute(
+        "CREATE TABLE IF NOT EXISTS SCHEMA_VERSION (
+            version INTEGER PRIMARY KEY,
+            description TEXT NOT NULL,
+            applied_at TEXT NOT NULL
+        )",
+        [],
+    )?;
+    Ok(())
+}
+
+fn current_version(conn: &Connection) -> SqlResult<u32> {
+    ensure_version_table(conn)?;
+    let version: Option<u32> = conn
+        .query_row("SELECT MAX(version) FROM SCHEMA_VERSION", [], |row| row.get(0))
+        .optional()?
+        .flatten();
+    Ok(version.unwrap_or(0))
+}
+
+// bring the database to the latest schema version, returns the version reached
+pub fn run_migrations(conn: &mut Connection) -> SqlResult<u32> {
+    let applied = current_version(conn)?;
+    let mut version = applied;
+
+    for migration in MIGRATIONS.iter().filter(|m| m.version > applied) {
+        println!("🗄️  Applying migration {}: {}", migration.version, migration.description);
+
+        // schema change and version record succeed or fail together
+        let tx = conn.transaction()?;
+        tx.execute_batch(migration.sql)?;
+        tx.execute(
+            "INSERT INTO SCHEMA_VERSION (version, description, applied_at) VALUES (?1, ?2, ?3)",
+            params![migration.version, migration.description, chrono::Utc::now().to_rfc3339()],
+        )?;
+        tx.commit()?;
+
+        version = migration.version;
+    }
+
+    println!("✅ Database schema at version {}", version);
+    Ok(version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn latest() -> u32 {
+        MIGRATIONS.last().unwrap().version
+    }
+
+    fn applied_versions(conn: &Connection) -> Vec<u32> {
+        let mut stmt = conn.prepare("SELECT version FROM SCHEMA_VERSION ORDER BY version").unwrap();
+        let rows = stmt.query_map([], |row| row.get(0)).unwrap();
+        rows.map(|r| r.unwrap()).collect()
+    }
+
+    fn applied_times(conn: &Connection) -> Vec<String> {
+        let mut stmt = conn.prepare("SELECT applied_at FROM SCHEMA_VERSION ORDER BY version").unwrap();
+        let rows = stmt.query_map([], |row| row.get(0)).unwrap();
+        rows.map(|r| r.unwrap()).collect()
+    }
+
+    #[test]
+    fn test_fresh_database_reaches_latest_version() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        assert_eq!(run_migrations(&mut conn).unwrap(), latest());
+        assert_eq!(applied_versions(&conn), (1..=latest()).collect::<Vec<_>>());
+
+        // the columns added by the last migrations are there
+        conn.execute(
+            "INSERT INTO METADATA (path, user_id, user_permissions, group_permissions, others_permissions, created_at, last_modified, type, inode_id)
+             VALUES ('a.txt', 1, 6, 4, 4, 'now', 'now', 0, NULL)",
+            [],
+        ).unwrap();
+        conn.execute("INSERT INTO USER (Username, Password, Quota_Bytes) VALUES ('u', 'p', 10)", []).unwrap();
+    }
+
+    #[test]
+    fn test_old_database_is_upgraded_in_order() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        ensure_version_table(&conn).unwrap();
+        for migration in MIGRATIONS.iter().filter(|m| m.version <= 2) {
+            conn.execute_batch(migration.sql).unwrap();
+            conn.execute(
+                "INSERT INTO SCHEMA_VERSION (version, description, applied_at) VALUES (?1, ?2, 'then')",
+                params![migration.version, migration.description],
+            ).unwrap();
+        }
+        conn.execute("INSERT INTO USER (Username, Password) VALUES ('old', 'p')", []).unwrap();
+        let user_id = conn.last_insert_rowid();
+        conn.execute(
+            "INSERT INTO METADATA (path, user_id, user_permissions, group_permissions, others_permissions, created_at, last_modified, type)
+             VALUES ('old.txt', ?1, 6, 4, 4, 'created', 'modified', 0)",
+            params![user_id],
+        ).unwrap();
+
+        assert_eq!(run_migrations(&mut conn).unwrap(), latest());
+        assert_eq!(applied_versions(&conn), (1..=latest()).collect::<Vec<_>>());
+
+        // 3 fills the owner and 4 the new times of the rows already there
+        let (owner_id, last_accessed): (i64, String) = conn.query_row(
+            "SELECT owner_id, last_accessed FROM METADATA WHERE path = 'old.txt'",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        ).unwrap();
+        assert_eq!(owner_id, user_id);
+        assert_eq!(last_accessed, "modified");
+    }
+
+    #[test]
+    fn test_second_run_changes_nothing() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        run_migrations(&mut conn).unwrap();
+        let applied_at = applied_times(&conn);
+
+        assert_eq!(run_migrations(&mut conn).unwrap(), latest());
+        assert_eq!(applied_versions(&conn), (1..=latest()).collect::<Vec<_>>());
+        assert_eq!(applied_times(&conn), applied_at);
+    }
+}
@@ -0,0 +1,127 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+// after this many failed logins of a username the next attempts are delayed
+const MAX_USER_FAILURES: u32 = 5;
+// an address can try more usernames before being delayed
+const MAX_IP_FAILURES: u32 = 20;
+// first delay, doubled at every further failure
+const BASE_BACKOFF: Duration = Duration::from_secs(1);
+// longest delay, i.e. the temporary lockout
+const MAX_BACKOFF: Duration = Duration::from_secs(15 * 60);
+// the failures are forgotten after this long without new ones
+const FAILURE_WINDOW: Duration = Duration::from_secs(15 * 60);
+
+struct Attempts {
+    failures: u32,
+    pending: u32,               // attempts let through and still checking the password
+    last_failure: Instant,
+    blocked_until: Option<Instant>,
+}
+
+// Failed logins per username and per client address.
+// Only the failures are counted, so the users that log in correctly are never slowed down.
+// An attempt is reserved before the password is checked: a burst of concurrent logins can't run
+// more checks than the failures still allowed
+pub struct LoginLimiter {
+    attempts: Mutex<HashMap<String, Attempts>>,
+}
+
+impl Default for LoginLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LoginLimiter {
+    pub fn new() -> Self {
+        Self {
+            attempts: Mutex::new(HashMap::new()),
+        }
+    }
+
+    // Err with the time to wait if the username or the address is blocked, otherwise the attempt is
+    // pending until record_failure, record_success or cancel
+    pub fn begin(&self, username: &str, ip: &str) -> Result<(), Duration> {
+        let mut attempts = self.attempts.lock().unwrap();
+        let now = Instant::now();
+        attempts.retain(|_, a| a.pending > 0 || now.duration_since(a.last_failure) < FAILURE_WINDOW);
+
+        let keys = Self::keys(username, ip);
+        let wait = keys.iter()
+            .filter_map(|(key, max_failures)| attempts.get(key).map(|a| (a, *max_failures)))
+            .filter_map(|(a, max_failures)| match a.blocked_until {
+                Some(until) if until > now => Some(until - now),
+                // the attempts in progress could already reach the limit; after it they go one at a time
+                _ if a.pending >= max_failures.saturating_sub(a.failures).max(1) => Some(BASE_BACKOFF),
+                _ => None,
+            })
+            .max();
+        if let Some(wait) = wait {
+            return Err(wait);
+        }
+
+        for (key, _) in keys {
+            Self::entry(&mut attempts, key, now).pending += 1;
+        }
+        Ok(())
+    }
+
+    pub fn record_failure(&self, username: &str, ip: &str) {
+        let mut attempts = self.attempts.lock().unwrap();
+        let now = Instant::now();
+
+        for (key, max_failures) in Self::keys(username, ip) {
+            let entry = Self::entry(&mut attempts, key.clone(), now);
+            entry.pending = entry.pending.saturating_sub(1);
+            entry.failures += 1;
+            entry.last_failure = now;
+            if entry.failures >= max_failures {
+                let backoff = BASE_BACKOFF
+                    .checked_mul(1 << (entry.failures - max_failures).min(20))
+                    .unwrap_or(MAX_BACKOFF)
+                    .min(MAX_BACKOFF);
+                entry.blocked_until = Some(now + backoff);
+                println!("🚫 Too many failed logins for {}, blocked for {:?}", key, backoff);
+            }
+        }
+    }
+
+    // a correct login clears the failures of the username, the ones of the address stay
+    pub fn record_success(&self, username: &str, ip: &str) {
+        self.cancel(username, ip);
+        self.attempts.lock().unwrap().remove(&Self::user_key(username));
+    }
+
+    // the attempt ended without telling if the password was right (e.g. an internal error)
+    pub fn cancel(&self, username: &str, ip: &str) {
+        let mut attempts = self.attempts.lock().unwrap();
+        for (key, _) in Self::keys(username, ip) {
+            if let Some(entry) = attempts.get_mut(&key) {
+                entry.pending = entry.pending.saturating_sub(1);
+            }
+        }
+    }
+
+    fn entry(attempts: &mut HashMap<String, Attempts>, key: String, now: Instant) -> &mut Attempts {
+        attempts.entry(key).or_insert(Attempts {
+            failures: 0,
+            pending: 0,
+            last_failure: now,
+            blocked_until: None,
+        })
+    }
+
+    fn keys(username: &str, ip: &str) -> [(String, u32); 2] {
+        [(Self::user_key(username), MAX_USER_FAILURES), (Self::ip_key(ip), MAX_IP_FAILURES)]
+    }
+
+    fn user_key(username: &str) -> String {
+        format!("user:{}", username)
+    }
+
+    fn ip_key(ip: &str) -> String {
+        format!("ip:{}", ip)
+    }
+}
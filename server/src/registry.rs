@@ -0,0 +1,130 @@
+use server::FileSystem;
+use rusqlite::Connection;
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+// filesystem of a single user, shared between the requests of that user
+pub type UserFileSystem = Arc<Mutex<FileSystem>>;
+
+struct RegistryEntry {
+    fs: UserFileSystem,
+    last_used: Instant,
+}
+
+// Registry of the per-user filesystems.
+// Every request resolves its own FileSystem from the user inside the JWT,
+// so different users can work at the same time without seeing each other's tree.
+pub struct FileSystemRegistry {
+    base_path: String,
+    connection: Arc<Mutex<Connection>>,
+    entries: Mutex<HashMap<i32, RegistryEntry>>,
+    // one lock for each user whose filesystem is being loaded, so the same tree
+    // is never built (and reconciled) twice at the same time
+    loading: Mutex<HashMap<i32, Arc<Mutex<()>>>>,
+    idle_timeout: Duration,
+}
+
+impl FileSystemRegistry {
+    pub fn new(base_path: &str, connection: Arc<Mutex<Connection>>, idle_timeout: Duration) -> Self {
+        Self {
+            base_path: base_path.to_string(),
+            connection,
+            entries: Mutex::new(HashMap::new()),
+            loading: Mutex::new(HashMap::new()),
+            idle_timeout,
+        }
+    }
+
+    // returns the filesystem of the user, building it from disk the first time it is needed.
+    // The tree is built without holding the registry lock, the other users are not blocked meanwhile
+    pub fn get(&self, username: &str, user_id: i32) -> UserFileSystem {
+        if let Some(fs) = self.cached(user_id) {
+            return fs;
+        }
+
+        let user_lock = self.loading.lock().unwrap().entry(user_id).or_default().clone();
+        let _loading = user_lock.lock().unwrap();
+        // another request of the same user may have loaded it while this one was waiting
+        if let Some(fs) = self.cached(user_id) {
+            return fs;
+        }
+
+        println!("📂 Loading filesystem for user {} (id: {})", username, user_id);
+        let fs = Arc::new(Mutex::new(self.create_user_filesystem(username, user_id)));
+        self.entries.lock().unwrap().insert(user_id, RegistryEntry {
+            fs: fs.clone(),
+            last_used: Instant::now(),
+        });
+        self.loading.lock().unwrap().remove(&user_id);
+        fs
+    }
+
+    fn cached(&self, user_id: i32) -> Option<UserFileSystem> {
+        let mut entries = self.entries.lock().unwrap();
+        entries.get_mut(&user_id).map(|entry| {
+            entry.last_used = Instant::now();
+            entry.fs.clone()
+        })
+    }
+
+    // remove the filesystems not used for longer than the idle timeout.
+    // A filesystem still referenced by a running request is never evicted.
+    pub fn evict_idle(&self) -> usize {
+        let mut entries = self.entries.lock().unwrap();
+        let before = entries.len();
+        entries.retain(|user_id, entry| {
+            let keep = entry.last_used.elapsed() < self.idle_timeout || Arc::strong_count(&entry.fs) > 1;
+            if !keep {
+                println!("🧹 Evicting idle filesystem of user {}", user_id);
+            }
+            keep
+        });
+        before - entries.len()
+    }
+
+    // the filesystem of a deleted account: with purge its directory is removed, otherwise it is
+    // moved to <base>-deleted/<username>-<id>, so a new account with the same name starts empty
+    pub fn remove_user(&self, username: &str, user_id: i32, purge: bool) -> Result<(), String> {
+        self.entries.lock().unwrap().remove(&user_id);
+
+        let user_path = format!("{}/{}", self.base_path, username);
+        if !std::path::Path::new(&user_path).exists() {
+            return Ok(());
+        }
+        if purge {
+            std::fs::remove_dir_all(&user_path).map_err(|e| e.to_string())?;
+            println!("🗑️  Removed the files of {}", username);
+        } else {
+            let archive = format!("{}-deleted", self.base_path);
+            std::fs::create_dir_all(&archive).map_err(|e| e.to_string())?;
+            let mut archived_path = format!("{}/{}-{}", archive, username, user_id);
+            // the ids start again from 1 if the database is recreated, never overwrite an old archive
+            if std::path::Path::new(&archived_path).exists() {
+                archived_path = format!("{}-{}", archived_path, chrono::Utc::now().timestamp_millis());
+            }
+            std::fs::rename(&user_path, &archived_path).map_err(|e| e.to_string())?;
+            println!("📦 Files of {} moved to {}", username, archived_path);
+        }
+        Ok(())
+    }
+
+    pub fn idle_timeout(&self) -> Duration {
+        self.idle_timeout
+    }
+
+    fn create_user_filesystem(&self, username: &str, user_id: i32) -> FileSystem {
+        let user_path = format!("{}/{}", self.base_path, username);
+        let mut fs = FileSystem::from_file_system(&user_path);
+        fs.set_side_effects(true);
+        fs.set_database(self.connection.clone());
+        fs.set_owner(user_id as i64);
+
+        // the directory may have been changed while the filesystem was not loaded
+        match fs.reconcile(user_id as i64) {
+            Ok(report) => println!("🔄 Reconciled filesystem of {}: {}", username, report),
+            Err(e) => println!("⚠️  Reconciliation failed for {}: {}", username, e),
+        }
+        fs
+    }
+}
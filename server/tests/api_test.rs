@@ -59,6 +59,32 @@ async fn cleanup(token: String) {
     .send().await.unwrap();
 }
 
+// register (if needed) and login a user different from the one used by setup
+async fn login_user(username: &str) -> String {
+    let client = reqwest::Client::new();
+
+    client.post("http://127.0.0.1:8080/auth/register")
+    .json(&serde_json::json!({
+        "username": username,
+        "password": "password"
+    }))
+    .send()
+    .await
+    .unwrap();
+
+    let res = client.post("http://127.0.0.1:8080/auth/login")
+    .json(&serde_json::json!({
+        "username": username,
+        "password": "password"
+    }))
+    .send()
+    .await
+    .unwrap();
+
+    let body: serde_json::Value = res.json().await.unwrap();
+    body["token"].as_str().unwrap().to_string()
+}
+
 // TESTS ON
 // GET /list/<path> – List directory contents
 
@@ -314,6 +340,53 @@ async fn test_read_file_dot_and_dotdot_combo() {
     cleanup(token).await;
 }
 
+#[tokio::test]
+#[serial_test::serial]
+async fn test_read_file_range() {
+    let token=setup().await;
+
+    let client = reqwest::Client::new();
+    // file1.txt contains "content"
+    let res = client
+        .get("http://127.0.0.1:8080/files/test_dir/file1.txt")
+        .bearer_auth(&token)
+        .header("Range", "bytes=1-3")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), reqwest::StatusCode::PARTIAL_CONTENT);
+    assert_eq!(res.headers()["content-range"], "bytes 1-3/7");
+    assert_eq!(res.text().await.unwrap(), "ont");
+
+    // open ended range and end past the size of the file
+    let res = client
+        .get("http://127.0.0.1:8080/files/test_dir/file1.txt")
+        .bearer_auth(&token)
+        .header("Range", "bytes=4-100")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), reqwest::StatusCode::PARTIAL_CONTENT);
+    assert_eq!(res.headers()["content-range"], "bytes 4-6/7");
+    assert_eq!(res.text().await.unwrap(), "ent");
+
+    // start past the end of the file
+    let res = client
+        .get("http://127.0.0.1:8080/files/test_dir/file1.txt")
+        .bearer_auth(&token)
+        .header("Range", "bytes=50-60")
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(res.status(), reqwest::StatusCode::RANGE_NOT_SATISFIABLE);
+    assert_eq!(res.headers()["content-range"], "bytes */7");
+
+    cleanup(token).await
+}
+
 #[tokio::test]
 #[serial_test::serial]
 async fn test_file_contents_not_found() {
@@ -695,6 +768,170 @@ async fn test_write_file_empty_content() {
     cleanup(token).await;
 }
 
+#[tokio::test]
+#[serial_test::serial]
+async fn test_write_file_binary_content() {
+    let token=setup().await;
+
+    let client = reqwest::Client::new();
+    // bytes that are not valid UTF-8
+    let content: Vec<u8> = vec![0x89, 0x50, 0x4e, 0x47, 0x00, 0xff, 0xfe, 0x0d, 0x0a, 0x80];
+    let res = client
+        .put("http://127.0.0.1:8080/files/test_dir/image.png")
+        .bearer_auth(&token)
+        .body(content.clone())
+        .send()
+        .await
+        .unwrap();
+
+    assert!(res.status().is_success());
+
+    // Read back the file, the bytes must be untouched
+    let res = client
+        .get("http://127.0.0.1:8080/files/test_dir/image.png")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+
+    assert!(res.status().is_success());
+    let body = res.bytes().await.unwrap();
+    assert_eq!(body.to_vec(), content);
+
+    cleanup(token).await;
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_write_file_large_streaming() {
+    let token=setup().await;
+
+    let client = reqwest::Client::new();
+    // bigger than the default limit of the buffered extractors
+    let content: Vec<u8> = (0..5 * 1024 * 1024).map(|i| (i % 251) as u8).collect();
+    let res = client
+        .put("http://127.0.0.1:8080/files/test_dir/big.bin")
+        .bearer_auth(&token)
+        .body(content.clone())
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+
+    let res = client
+        .get("http://127.0.0.1:8080/files/test_dir/big.bin")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    let body = res.bytes().await.unwrap();
+    assert_eq!(body.len(), content.len());
+    assert!(body.to_vec() == content);
+
+    // only the uploaded file is in the directory, no temporary leftovers
+    let res = client
+        .get("http://127.0.0.1:8080/list/test_dir")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    let body: serde_json::Value = res.json().await.unwrap();
+    let files: Vec<serde_json::Value> = body.as_array().unwrap().to_vec();
+    let big = files.iter().find(|f| f["name"] == "big.bin").unwrap();
+    assert_eq!(big["size"], 5 * 1024 * 1024);
+    assert!(!files.iter().any(|f| f["name"].as_str().unwrap().contains(".upload-")));
+
+    cleanup(token).await;
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_patch_file_at_offset() {
+    let token=setup().await;
+
+    let client = reqwest::Client::new();
+    // a file written in two chunks, like the kernel does
+    let res = client
+        .patch("http://127.0.0.1:8080/files/test_dir/chunks.txt?offset=0")
+        .bearer_auth(&token)
+        .body("hello ")
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+
+    let res = client
+        .patch("http://127.0.0.1:8080/files/test_dir/chunks.txt?offset=6")
+        .bearer_auth(&token)
+        .body("world")
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(body["size"], 11);
+
+    // overwrite in the middle
+    let res = client
+        .patch("http://127.0.0.1:8080/files/test_dir/chunks.txt?offset=0")
+        .bearer_auth(&token)
+        .body("HELLO")
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+
+    let res = client
+        .get("http://127.0.0.1:8080/files/test_dir/chunks.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), "HELLO world");
+
+    // the metadata has the size of the whole file
+    let res = client
+        .get("http://127.0.0.1:8080/lookup/test_dir/chunks.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    let body: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(body["size"], 11);
+
+    // invalid offset
+    let res = client
+        .patch("http://127.0.0.1:8080/files/test_dir/chunks.txt?offset=-1")
+        .bearer_auth(&token)
+        .body("x")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    // the end of the write doesn't fit in a u64
+    let res = client
+        .patch("http://127.0.0.1:8080/files/test_dir/chunks.txt?offset=18446744073709551615")
+        .bearer_auth(&token)
+        .body("x")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    // the filesystem of the user still works
+    let res = client
+        .get("http://127.0.0.1:8080/files/test_dir/chunks.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), "HELLO world");
+
+    cleanup(token).await;
+}
+
 #[tokio::test]
 #[serial_test::serial]
 async fn test_write_file_dot() {
@@ -1540,4 +1777,2041 @@ async fn test_delete_directory_not_found() {
     assert!(body.contains("not found"));
 
     cleanup(token).await;
-}
\ No newline at end of file
+}
+
+// TESTS ON
+// per-user filesystems
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_login_of_other_user_keeps_filesystem() {
+    let token=setup().await;
+
+    let client = reqwest::Client::new();
+    // another user logs in after testuser and writes in its own tree
+    let other_token = login_user("otheruser").await;
+    let res = client
+        .put("http://127.0.0.1:8080/files/other_file.txt")
+        .bearer_auth(&other_token)
+        .body("other content")
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+
+    // the token of testuser must still see its own tree
+    let res = client
+        .get("http://127.0.0.1:8080/files/test_dir/file1.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    assert_eq!(res.text().await.unwrap(), "content");
+
+    // and must not see the file of the other user
+    let res = client
+        .get("http://127.0.0.1:8080/files/other_file.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::NOT_FOUND);
+
+    client.delete("http://127.0.0.1:8080/files/other_file.txt")
+    .bearer_auth(&other_token)
+    .send().await.unwrap();
+
+    cleanup(token).await;
+}
+
+// TESTS ON
+// POST /rename – Rename or move a file or directory
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_rename_file_to_other_directory() {
+    let token=setup().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post("http://127.0.0.1:8080/rename")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "from": "test_dir/file1.txt", "to": "test_dir/dir1/moved.txt" }))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+
+    // the content is reachable from the new path
+    let res = client
+        .get("http://127.0.0.1:8080/files/test_dir/dir1/moved.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    assert_eq!(res.text().await.unwrap(), "content");
+
+    // and the metadata follows the item
+    let res = client
+        .get("http://127.0.0.1:8080/lookup/test_dir/dir1/moved.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+
+    let res = client
+        .get("http://127.0.0.1:8080/lookup/test_dir/file1.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::NOT_FOUND);
+
+    cleanup(token).await;
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_rename_directory_with_contents() {
+    let token=setup().await;
+
+    let client = reqwest::Client::new();
+    client
+        .put("http://127.0.0.1:8080/files/test_dir/dir1/inner.txt")
+        .bearer_auth(&token)
+        .body("inner content")
+        .send()
+        .await
+        .unwrap();
+
+    let res = client
+        .post("http://127.0.0.1:8080/rename")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "from": "test_dir/dir1", "to": "test_dir/renamed_dir" }))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+
+    // the descendants have the new path as well
+    let res = client
+        .get("http://127.0.0.1:8080/list/test_dir/renamed_dir")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    let body: serde_json::Value = res.json().await.unwrap();
+    let files: Vec<serde_json::Value> = body.as_array().unwrap().to_vec();
+    assert!(files.iter().any(|f| f["name"] == "inner.txt"));
+
+    let res = client
+        .get("http://127.0.0.1:8080/files/test_dir/renamed_dir/inner.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), "inner content");
+
+    cleanup(token).await;
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_rename_directory_keeps_similar_siblings() {
+    let token=setup().await;
+
+    let client = reqwest::Client::new();
+    // "docs" differs only in the case, "axb" matches "a_b" if '_' is read as a wildcard
+    for (dir, file) in [("Docs", "a.txt"), ("docs", "b.txt"), ("a_b", "c.txt"), ("axb", "d.txt")] {
+        let res = client
+            .post(format!("http://127.0.0.1:8080/mkdir/test_dir/{}", dir))
+            .bearer_auth(&token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+        let res = client
+            .put(format!("http://127.0.0.1:8080/files/test_dir/{}/{}?permissions=600", dir, file))
+            .bearer_auth(&token)
+            .body("content")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+    }
+
+    for (from, to) in [("Docs", "Papers"), ("a_b", "a_c")] {
+        let res = client
+            .post("http://127.0.0.1:8080/rename")
+            .bearer_auth(&token)
+            .json(&serde_json::json!({ "from": format!("test_dir/{}", from), "to": format!("test_dir/{}", to) }))
+            .send()
+            .await
+            .unwrap();
+        assert!(res.status().is_success());
+    }
+
+    // the siblings keep their paths and their metadata
+    for path in ["docs/b.txt", "axb/d.txt", "Papers/a.txt", "a_c/c.txt"] {
+        let res = client
+            .get(format!("http://127.0.0.1:8080/lookup/test_dir/{}", path))
+            .bearer_auth(&token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::OK, "{}", path);
+        let info: serde_json::Value = res.json().await.unwrap();
+        assert_eq!(info["permissions"], 0o600, "{}", path);
+    }
+
+    cleanup(token).await;
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_rename_errors() {
+    let token=setup().await;
+
+    let client = reqwest::Client::new();
+    // source does not exist
+    let res = client
+        .post("http://127.0.0.1:8080/rename")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "from": "test_dir/missing.txt", "to": "test_dir/other.txt" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::NOT_FOUND);
+
+    // a file can't replace a directory
+    let res = client
+        .post("http://127.0.0.1:8080/rename")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "from": "test_dir/file1.txt", "to": "test_dir/dir1" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::CONFLICT);
+
+    // a directory can't be moved inside itself
+    let res = client
+        .post("http://127.0.0.1:8080/rename")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "from": "test_dir", "to": "test_dir/dir1/test_dir" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    cleanup(token).await;
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_symlink_create_and_read() {
+    let token=setup().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post("http://127.0.0.1:8080/symlink/test_dir/dir1/link.txt")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "target": "../file1.txt" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    // the target is returned as it was written
+    let res = client
+        .get("http://127.0.0.1:8080/readlink/test_dir/dir1/link.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "../file1.txt");
+
+    // lookup describes the link, not the target
+    let res = client
+        .get("http://127.0.0.1:8080/lookup/test_dir/dir1/link.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    let info: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(info["is_symlink"], true);
+    assert_eq!(info["is_directory"], false);
+
+    // reading through the link reads the target
+    let res = client
+        .get("http://127.0.0.1:8080/files/test_dir/dir1/link.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), "content");
+
+    // a regular file is not a link
+    let res = client
+        .get("http://127.0.0.1:8080/readlink/test_dir/file1.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    // deleting the link leaves the target in place
+    let res = client
+        .delete("http://127.0.0.1:8080/files/test_dir/dir1/link.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    let res = client
+        .get("http://127.0.0.1:8080/files/test_dir/file1.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    cleanup(token).await;
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_symlink_outside_root_rejected() {
+    let token=setup().await;
+
+    let client = reqwest::Client::new();
+    for target in ["/etc/passwd", "../../../etc/passwd", "../.."] {
+        let res = client
+            .post("http://127.0.0.1:8080/symlink/test_dir/escape")
+            .bearer_auth(&token)
+            .json(&serde_json::json!({ "target": target }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST, "target {}", target);
+    }
+
+    // the name is already taken
+    let res = client
+        .post("http://127.0.0.1:8080/symlink/test_dir/file1.txt")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "target": "dir1" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::CONFLICT);
+
+    // a loop of links doesn't hang the server
+    for (name, target) in [("loop_a", "loop_b"), ("loop_b", "loop_a")] {
+        let res = client
+            .post(format!("http://127.0.0.1:8080/symlink/test_dir/{}", name))
+            .bearer_auth(&token)
+            .json(&serde_json::json!({ "target": target }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+    }
+    let res = client
+        .get("http://127.0.0.1:8080/files/test_dir/loop_a")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::NOT_FOUND);
+
+    cleanup(token).await;
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_hard_link_shares_content_and_counts() {
+    let token=setup().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post("http://127.0.0.1:8080/link/test_dir/dir1/same.txt")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "target": "test_dir/file1.txt" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    // both names report two links
+    for path in ["test_dir/file1.txt", "test_dir/dir1/same.txt"] {
+        let res = client
+            .get(format!("http://127.0.0.1:8080/lookup/{}", path))
+            .bearer_auth(&token)
+            .send()
+            .await
+            .unwrap();
+        let info: serde_json::Value = res.json().await.unwrap();
+        assert_eq!(info["links"], 2, "links of {}", path);
+    }
+
+    // writing through one name is seen through the other, size included
+    client
+        .put("http://127.0.0.1:8080/files/test_dir/dir1/same.txt")
+        .bearer_auth(&token)
+        .body("new longer content")
+        .send()
+        .await
+        .unwrap();
+    let res = client
+        .get("http://127.0.0.1:8080/files/test_dir/file1.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), "new longer content");
+    let res = client
+        .get("http://127.0.0.1:8080/lookup/test_dir/file1.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    let info: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(info["size"], 18);
+
+    // removing a name leaves the other one with a single link
+    client
+        .delete("http://127.0.0.1:8080/files/test_dir/dir1/same.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    let res = client
+        .get("http://127.0.0.1:8080/lookup/test_dir/file1.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    let info: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(info["links"], 1);
+
+    // directories can't be hard linked
+    let res = client
+        .post("http://127.0.0.1:8080/link/test_dir/dir2")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "target": "test_dir/dir1" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    cleanup(token).await;
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_directory_link_count() {
+    let token=setup().await;
+
+    let client = reqwest::Client::new();
+    client.post("http://127.0.0.1:8080/mkdir/test_dir/dir2")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+
+    let res = client
+        .get("http://127.0.0.1:8080/list/test_dir")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    let files: Vec<serde_json::Value> = res.json().await.unwrap();
+    let dir1 = files.iter().find(|f| f["name"] == "dir1").unwrap();
+    assert_eq!(dir1["links"], 2);
+
+    // test_dir contains dir1 and dir2
+    let res = client
+        .get("http://127.0.0.1:8080/lookup/test_dir")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    let info: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(info["links"], 4);
+
+    cleanup(token).await;
+}
+
+// TESTS ON
+// groups
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_admin_group_management() {
+    let token=setup().await;
+    let admin_token = login_user("admin").await;
+
+    let client = reqwest::Client::new();
+
+    // only the admins manage groups
+    let res = client
+        .post("http://127.0.0.1:8080/admin/groups")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "name": "project" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::FORBIDDEN);
+
+    client.delete("http://127.0.0.1:8080/admin/groups/project")
+        .bearer_auth(&admin_token)
+        .send()
+        .await
+        .unwrap();
+    let res = client
+        .post("http://127.0.0.1:8080/admin/groups")
+        .bearer_auth(&admin_token)
+        .json(&serde_json::json!({ "name": "project" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    let res = client
+        .post("http://127.0.0.1:8080/admin/groups")
+        .bearer_auth(&admin_token)
+        .json(&serde_json::json!({ "name": "project" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::CONFLICT);
+
+    let res = client
+        .put("http://127.0.0.1:8080/admin/groups/project/members/testuser")
+        .bearer_auth(&admin_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    let res = client
+        .get("http://127.0.0.1:8080/admin/groups")
+        .bearer_auth(&admin_token)
+        .send()
+        .await
+        .unwrap();
+    let groups: Vec<serde_json::Value> = res.json().await.unwrap();
+    let project = groups.iter().find(|g| g["name"] == "project").unwrap();
+    assert_eq!(project["members"], serde_json::json!(["testuser"]));
+
+    let res = client
+        .delete("http://127.0.0.1:8080/admin/groups/project/members/testuser")
+        .bearer_auth(&admin_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    let res = client
+        .delete("http://127.0.0.1:8080/admin/groups/project/members/testuser")
+        .bearer_auth(&admin_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::NOT_FOUND);
+
+    let res = client
+        .delete("http://127.0.0.1:8080/admin/groups/project")
+        .bearer_auth(&admin_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    cleanup(token).await;
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_change_group_of_item() {
+    let token=setup().await;
+    let admin_token = login_user("admin").await;
+
+    let client = reqwest::Client::new();
+    for name in ["project", "strangers"] {
+        client.delete(format!("http://127.0.0.1:8080/admin/groups/{}", name))
+            .bearer_auth(&admin_token)
+            .send()
+            .await
+            .unwrap();
+        client.post("http://127.0.0.1:8080/admin/groups")
+            .bearer_auth(&admin_token)
+            .json(&serde_json::json!({ "name": name }))
+            .send()
+            .await
+            .unwrap();
+    }
+    client.put("http://127.0.0.1:8080/admin/groups/project/members/testuser")
+        .bearer_auth(&admin_token)
+        .send()
+        .await
+        .unwrap();
+
+    // without a group the item shows the default one
+    let res = client
+        .get("http://127.0.0.1:8080/lookup/test_dir")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    let info: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(info["group"], "users");
+
+    let res = client
+        .post("http://127.0.0.1:8080/chgrp/test_dir")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "group": "project" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    let res = client
+        .get("http://127.0.0.1:8080/lookup/test_dir")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    let info: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(info["group"], "project");
+
+    // new items inherit the group of their directory
+    client.put("http://127.0.0.1:8080/files/test_dir/new_file.txt")
+        .bearer_auth(&token)
+        .body("x")
+        .send()
+        .await
+        .unwrap();
+    let res = client
+        .get("http://127.0.0.1:8080/list/test_dir")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    let files: Vec<serde_json::Value> = res.json().await.unwrap();
+    let new_file = files.iter().find(|f| f["name"] == "new_file.txt").unwrap();
+    assert_eq!(new_file["group"], "project");
+
+    // the owner can't give the item to a group it doesn't belong to
+    let res = client
+        .post("http://127.0.0.1:8080/chgrp/test_dir")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "group": "strangers" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::FORBIDDEN);
+
+    let res = client
+        .post("http://127.0.0.1:8080/chgrp/test_dir")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "group": "missing" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::NOT_FOUND);
+
+    // deleting the group leaves the items without group
+    client.delete("http://127.0.0.1:8080/admin/groups/project")
+        .bearer_auth(&admin_token)
+        .send()
+        .await
+        .unwrap();
+    client.delete("http://127.0.0.1:8080/admin/groups/strangers")
+        .bearer_auth(&admin_token)
+        .send()
+        .await
+        .unwrap();
+    let res = client
+        .get("http://127.0.0.1:8080/lookup/test_dir")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    let info: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(info["group"], "users");
+
+    cleanup(token).await;
+}
+
+// TESTS ON
+// access to the tree of another user (shared/<username>)
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_shared_access_follows_permissions() {
+    let token=setup().await;
+    let colleague_token = login_user("colleague").await;
+
+    let client = reqwest::Client::new();
+    client.put("http://127.0.0.1:8080/files/test_dir/private.txt?permissions=600")
+        .bearer_auth(&token)
+        .body("secret")
+        .send()
+        .await
+        .unwrap();
+    client.post("http://127.0.0.1:8080/mkdir/test_dir/private_dir?permissions=700")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+
+    // every user sees the others in the shared directory
+    let res = client
+        .get("http://127.0.0.1:8080/list/shared")
+        .bearer_auth(&colleague_token)
+        .send()
+        .await
+        .unwrap();
+    let users: Vec<serde_json::Value> = res.json().await.unwrap();
+    assert!(users.iter().any(|u| u["name"] == "testuser" && u["is_directory"] == true));
+
+    // world readable items can be read
+    let res = client
+        .get("http://127.0.0.1:8080/list/shared/testuser/test_dir")
+        .bearer_auth(&colleague_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    let res = client
+        .get("http://127.0.0.1:8080/files/shared/testuser/test_dir/file1.txt")
+        .bearer_auth(&colleague_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "content");
+
+    let res = client
+        .get("http://127.0.0.1:8080/lookup/shared/testuser/test_dir/file1.txt")
+        .bearer_auth(&colleague_token)
+        .send()
+        .await
+        .unwrap();
+    let info: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(info["owner"], "testuser");
+
+    // private items can't
+    let res = client
+        .get("http://127.0.0.1:8080/files/shared/testuser/test_dir/private.txt")
+        .bearer_auth(&colleague_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::FORBIDDEN);
+    let res = client
+        .get("http://127.0.0.1:8080/list/shared/testuser/test_dir/private_dir")
+        .bearer_auth(&colleague_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::FORBIDDEN);
+
+    // without write permission nothing can be changed
+    let res = client
+        .put("http://127.0.0.1:8080/files/shared/testuser/test_dir/intruder.txt")
+        .bearer_auth(&colleague_token)
+        .body("x")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::FORBIDDEN);
+    let res = client
+        .patch("http://127.0.0.1:8080/files/shared/testuser/test_dir/file1.txt?offset=0")
+        .bearer_auth(&colleague_token)
+        .body("X")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::FORBIDDEN);
+    let res = client
+        .delete("http://127.0.0.1:8080/files/shared/testuser/test_dir/file1.txt")
+        .bearer_auth(&colleague_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::FORBIDDEN);
+    let res = client
+        .post("http://127.0.0.1:8080/mkdir/shared/testuser/intruder_dir")
+        .bearer_auth(&colleague_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::FORBIDDEN);
+
+    cleanup(token).await;
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_group_members_write_in_shared_folder() {
+    let token=setup().await;
+    let colleague_token = login_user("colleague").await;
+    let outsider_token = login_user("outsider").await;
+    let admin_token = login_user("admin").await;
+
+    let client = reqwest::Client::new();
+    client.delete("http://127.0.0.1:8080/admin/groups/team")
+        .bearer_auth(&admin_token)
+        .send()
+        .await
+        .unwrap();
+    client.post("http://127.0.0.1:8080/admin/groups")
+        .bearer_auth(&admin_token)
+        .json(&serde_json::json!({ "name": "team" }))
+        .send()
+        .await
+        .unwrap();
+    for member in ["testuser", "colleague"] {
+        client.put(format!("http://127.0.0.1:8080/admin/groups/team/members/{}", member))
+            .bearer_auth(&admin_token)
+            .send()
+            .await
+            .unwrap();
+    }
+
+    // a project folder writable by the group
+    client.post("http://127.0.0.1:8080/mkdir/test_dir/project?permissions=775")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    let res = client
+        .post("http://127.0.0.1:8080/chgrp/test_dir/project")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "group": "team" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    let res = client
+        .put("http://127.0.0.1:8080/files/shared/testuser/test_dir/project/notes.txt")
+        .bearer_auth(&colleague_token)
+        .body("from a colleague")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    // the new file belongs to who created it and to the group of the folder
+    let res = client
+        .get("http://127.0.0.1:8080/lookup/test_dir/project/notes.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    let info: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(info["owner"], "colleague");
+    assert_eq!(info["group"], "team");
+
+    let res = client
+        .put("http://127.0.0.1:8080/files/shared/testuser/test_dir/project/other.txt")
+        .bearer_auth(&outsider_token)
+        .body("x")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::FORBIDDEN);
+
+    // items can't be moved between trees
+    let res = client
+        .post("http://127.0.0.1:8080/rename")
+        .bearer_auth(&colleague_token)
+        .json(&serde_json::json!({ "from": "shared/testuser/test_dir/project/notes.txt", "to": "notes.txt" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    client.delete("http://127.0.0.1:8080/admin/groups/team")
+        .bearer_auth(&admin_token)
+        .send()
+        .await
+        .unwrap();
+    cleanup(token).await;
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_shared_delete_ignores_similar_siblings() {
+    let token=setup().await;
+    let colleague_token = login_user("colleague").await;
+
+    let client = reqwest::Client::new();
+    let res = client.post("http://127.0.0.1:8080/mkdir/test_dir/box?permissions=777")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    // the owner of the tree has "axb" and "shared", that look like the folders of the colleague
+    // to a LIKE: '_' matches any character and the case is ignored
+    for dir in ["axb", "shared"] {
+        let res = client
+            .post(format!("http://127.0.0.1:8080/mkdir/test_dir/box/{}", dir))
+            .bearer_auth(&token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+        let res = client
+            .put(format!("http://127.0.0.1:8080/files/test_dir/box/{}/own.txt", dir))
+            .bearer_auth(&token)
+            .body("x")
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+    }
+    for dir in ["a_b", "Shared"] {
+        let res = client
+            .post(format!("http://127.0.0.1:8080/mkdir/shared/testuser/test_dir/box/{}", dir))
+            .bearer_auth(&colleague_token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+    }
+
+    // the colleague removes its own folders, the ones of the owner are untouched
+    for dir in ["a_b", "Shared"] {
+        let res = client
+            .delete(format!("http://127.0.0.1:8080/files/shared/testuser/test_dir/box/{}", dir))
+            .bearer_auth(&colleague_token)
+            .send()
+            .await
+            .unwrap();
+        assert!(res.status().is_success(), "{}", dir);
+    }
+    // and so does the owner
+    let res = client.post("http://127.0.0.1:8080/mkdir/test_dir/box/a_b")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    let res = client.delete("http://127.0.0.1:8080/files/test_dir/box/a_b")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    for dir in ["axb", "shared"] {
+        let res = client
+            .get(format!("http://127.0.0.1:8080/lookup/test_dir/box/{}/own.txt", dir))
+            .bearer_auth(&token)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::OK, "{}", dir);
+        let info: serde_json::Value = res.json().await.unwrap();
+        assert_eq!(info["owner"], "testuser");
+    }
+
+    cleanup(token).await;
+}
+
+// TESTS ON
+// chmod and chown (POST /metadata)
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_chmod_item() {
+    let token=setup().await;
+    let colleague_token = login_user("colleague").await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post("http://127.0.0.1:8080/metadata/test_dir/file1.txt")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "permissions": "600" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    let info: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(info["permissions"], 0o600);
+
+    // the new permissions are stored
+    let res = client
+        .get("http://127.0.0.1:8080/lookup/test_dir/file1.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    let info: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(info["permissions"], 0o600);
+
+    // and they are enforced for the other users
+    let res = client
+        .get("http://127.0.0.1:8080/files/shared/testuser/test_dir/file1.txt")
+        .bearer_auth(&colleague_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::FORBIDDEN);
+
+    // only the owner can change them
+    let res = client
+        .post("http://127.0.0.1:8080/metadata/shared/testuser/test_dir")
+        .bearer_auth(&colleague_token)
+        .json(&serde_json::json!({ "permissions": "666" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::FORBIDDEN);
+
+    let res = client
+        .post("http://127.0.0.1:8080/metadata/test_dir/file1.txt")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "permissions": "rw-r--r--" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    let res = client
+        .post("http://127.0.0.1:8080/metadata/test_dir/missing.txt")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "permissions": "644" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::NOT_FOUND);
+
+    cleanup(token).await;
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_chown_item() {
+    let token=setup().await;
+    let colleague_token = login_user("colleague").await;
+
+    let client = reqwest::Client::new();
+
+    // a user can't give away the items in the tree of someone else
+    let res = client
+        .post("http://127.0.0.1:8080/metadata/shared/testuser/test_dir/file1.txt")
+        .bearer_auth(&colleague_token)
+        .json(&serde_json::json!({ "owner": "colleague" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::FORBIDDEN);
+
+    let res = client
+        .post("http://127.0.0.1:8080/metadata/test_dir/file1.txt")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "owner": "colleague", "permissions": "600" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    let info: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(info["owner"], "colleague");
+
+    // the new owner gets the owner bits
+    let res = client
+        .get("http://127.0.0.1:8080/files/shared/testuser/test_dir/file1.txt")
+        .bearer_auth(&colleague_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(res.text().await.unwrap(), "content");
+
+    let res = client
+        .post("http://127.0.0.1:8080/metadata/test_dir/file1.txt")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "owner": "nobody_like_this" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::NOT_FOUND);
+
+    cleanup(token).await;
+}
+
+// TESTS ON
+// truncate
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_truncate_file() {
+    let token=setup().await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post("http://127.0.0.1:8080/truncate/test_dir/file1.txt?size=3")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    let res = client
+        .get("http://127.0.0.1:8080/files/test_dir/file1.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), "con");
+
+    let res = client
+        .get("http://127.0.0.1:8080/lookup/test_dir/file1.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    let info: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(info["size"], 3);
+
+    // a bigger size fills the file with zeros
+    let res = client
+        .post("http://127.0.0.1:8080/truncate/test_dir/file1.txt?size=5")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    let res = client
+        .get("http://127.0.0.1:8080/files/test_dir/file1.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.bytes().await.unwrap().as_ref(), b"con\0\0");
+
+    let res = client
+        .post("http://127.0.0.1:8080/truncate/test_dir?size=0")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    let res = client
+        .post("http://127.0.0.1:8080/truncate/test_dir/file1.txt?size=-1")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    let res = client
+        .post("http://127.0.0.1:8080/truncate/test_dir/missing.txt?size=0")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::NOT_FOUND);
+
+    cleanup(token).await;
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_truncate_needs_write_permission() {
+    let token=setup().await;
+    let colleague_token = login_user("colleague").await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .post("http://127.0.0.1:8080/truncate/shared/testuser/test_dir/file1.txt?size=0")
+        .bearer_auth(&colleague_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::FORBIDDEN);
+
+    let res = client
+        .get("http://127.0.0.1:8080/files/test_dir/file1.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), "content");
+
+    cleanup(token).await;
+}
+
+// TESTS ON
+// access, change and creation times (utimens through POST /metadata)
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_set_times_of_item() {
+    let token=setup().await;
+    let colleague_token = login_user("colleague").await;
+
+    let client = reqwest::Client::new();
+    let res = client
+        .get("http://127.0.0.1:8080/lookup/test_dir/file1.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    let info: serde_json::Value = res.json().await.unwrap();
+    // all the times are full RFC3339 timestamps
+    let created = info["created"].as_str().unwrap().to_string();
+    for field in ["created", "accessed", "changed", "modified"] {
+        assert!(chrono::DateTime::parse_from_rfc3339(info[field].as_str().unwrap()).is_ok());
+    }
+
+    let res = client
+        .post("http://127.0.0.1:8080/metadata/test_dir/file1.txt")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "atime": "2020-01-02T03:04:05Z", "mtime": "2021-06-07T08:09:10Z" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    let info: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(info["modified"], "2021-06-07T08:09:10Z");
+    assert_eq!(info["accessed"], "2020-01-02T03:04:05Z");
+    // the creation time doesn't change
+    assert_eq!(info["created"].as_str().unwrap(), created);
+
+    // reading the content updates an access time older than the last modification
+    client.get("http://127.0.0.1:8080/files/test_dir/file1.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    let res = client
+        .get("http://127.0.0.1:8080/lookup/test_dir/file1.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    let info: serde_json::Value = res.json().await.unwrap();
+    assert_ne!(info["accessed"], "2020-01-02T03:04:05Z");
+    assert_eq!(info["modified"], "2021-06-07T08:09:10Z");
+
+    // without write access the times can't be changed
+    let res = client
+        .post("http://127.0.0.1:8080/metadata/shared/testuser/test_dir/file1.txt")
+        .bearer_auth(&colleague_token)
+        .json(&serde_json::json!({ "mtime": "2020-01-02T03:04:05Z" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::FORBIDDEN);
+
+    cleanup(token).await;
+}
+
+// TESTS ON
+// POST /auth/refresh and POST /auth/logout
+
+async fn login_session(username: &str) -> serde_json::Value {
+    login_user(username).await;
+    let client = reqwest::Client::new();
+    let res = client.post("http://127.0.0.1:8080/auth/login")
+        .json(&serde_json::json!({ "username": username, "password": "password" }))
+        .send()
+        .await
+        .unwrap();
+    res.json().await.unwrap()
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_refresh_token() {
+    let session = login_session("sessionuser").await;
+    assert!(session["expires_in"].as_u64().unwrap() > 0);
+    let refresh_token = session["refresh_token"].as_str().unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client.post("http://127.0.0.1:8080/auth/refresh")
+        .json(&serde_json::json!({ "refresh_token": refresh_token }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    let refreshed: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(refreshed["username"], "sessionuser");
+    assert_ne!(refreshed["refresh_token"], session["refresh_token"]);
+
+    // the new token works
+    let res = client.get("http://127.0.0.1:8080/list/")
+        .bearer_auth(refreshed["token"].as_str().unwrap())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    // a refresh token can be used only once
+    let res = client.post("http://127.0.0.1:8080/auth/refresh")
+        .json(&serde_json::json!({ "refresh_token": refresh_token }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let res = client.post("http://127.0.0.1:8080/auth/refresh")
+        .json(&serde_json::json!({ "refresh_token": "not-a-refresh-token" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::UNAUTHORIZED);
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_logout_revokes_tokens() {
+    let session = login_session("sessionuser").await;
+    let token = session["token"].as_str().unwrap();
+    let refresh_token = session["refresh_token"].as_str().unwrap();
+    let other_session = login_session("sessionuser").await;
+
+    let client = reqwest::Client::new();
+    let res = client.post("http://127.0.0.1:8080/auth/logout")
+        .bearer_auth(token)
+        .json(&serde_json::json!({ "refresh_token": refresh_token }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    // neither the token nor its refresh token can be used anymore
+    let res = client.get("http://127.0.0.1:8080/list/")
+        .bearer_auth(token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::UNAUTHORIZED);
+    let res = client.post("http://127.0.0.1:8080/auth/refresh")
+        .json(&serde_json::json!({ "refresh_token": refresh_token }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    // the other sessions are still open
+    let res = client.get("http://127.0.0.1:8080/list/")
+        .bearer_auth(other_session["token"].as_str().unwrap())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    // without a refresh token all the sessions are closed
+    let res = client.post("http://127.0.0.1:8080/auth/logout")
+        .bearer_auth(other_session["token"].as_str().unwrap())
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    let res = client.post("http://127.0.0.1:8080/auth/refresh")
+        .json(&serde_json::json!({ "refresh_token": other_session["refresh_token"] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let res = client.post("http://127.0.0.1:8080/auth/logout")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::UNAUTHORIZED);
+}
+
+// TESTS ON
+// account management
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_change_password() {
+    let session = login_session("pwuser").await;
+    let token = session["token"].as_str().unwrap();
+
+    let client = reqwest::Client::new();
+    let res = client.post("http://127.0.0.1:8080/auth/password")
+        .bearer_auth(token)
+        .json(&serde_json::json!({ "old_password": "wrong", "new_password": "new-password" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::FORBIDDEN);
+
+    let res = client.post("http://127.0.0.1:8080/auth/password")
+        .bearer_auth(token)
+        .json(&serde_json::json!({ "old_password": "password", "new_password": "short" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    let res = client.post("http://127.0.0.1:8080/auth/password")
+        .bearer_auth(token)
+        .json(&serde_json::json!({ "old_password": "password", "new_password": "new-password" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    // only the new password works, the other sessions can't be refreshed
+    let res = client.post("http://127.0.0.1:8080/auth/login")
+        .json(&serde_json::json!({ "username": "pwuser", "password": "password" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::UNAUTHORIZED);
+    let res = client.post("http://127.0.0.1:8080/auth/login")
+        .json(&serde_json::json!({ "username": "pwuser", "password": "new-password" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    let res = client.post("http://127.0.0.1:8080/auth/refresh")
+        .json(&serde_json::json!({ "refresh_token": session["refresh_token"] }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    // back to the password used by the other tests
+    let res = client.post("http://127.0.0.1:8080/auth/password")
+        .bearer_auth(token)
+        .json(&serde_json::json!({ "old_password": "new-password", "new_password": "password" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_delete_own_account() {
+    let token = login_user("leaving").await;
+
+    let client = reqwest::Client::new();
+    client.put("http://127.0.0.1:8080/files/old_notes.txt")
+        .bearer_auth(&token)
+        .body("notes")
+        .send()
+        .await
+        .unwrap();
+
+    let res = client.delete("http://127.0.0.1:8080/auth/account")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "password": "wrong" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::FORBIDDEN);
+
+    let res = client.delete("http://127.0.0.1:8080/auth/account")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "password": "password" }))
+        .send()
+        .await
+        .unwrap();
+    let status = res.status(); assert_eq!(status, reqwest::StatusCode::OK, "{:?}", res.text().await);
+
+    // the token of a deleted account doesn't work anymore
+    let res = client.get("http://127.0.0.1:8080/list/")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    // a new account with the same name doesn't get the old files
+    let token = login_user("leaving").await;
+    let res = client.get("http://127.0.0.1:8080/list/")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    let files: Vec<serde_json::Value> = res.json().await.unwrap();
+    assert!(!files.iter().any(|f| f["name"] == "old_notes.txt"));
+
+    client.delete("http://127.0.0.1:8080/auth/account")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "password": "password", "purge": true }))
+        .send()
+        .await
+        .unwrap();
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_admin_user_management() {
+    let admin_token = login_user("admin").await;
+    let token = login_user("doomed").await;
+
+    let client = reqwest::Client::new();
+    client.put("http://127.0.0.1:8080/files/doomed.txt")
+        .bearer_auth(&token)
+        .body("x")
+        .send()
+        .await
+        .unwrap();
+
+    // only the admins can list and delete the accounts
+    let res = client.get("http://127.0.0.1:8080/admin/users")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::FORBIDDEN);
+    let res = client.delete("http://127.0.0.1:8080/admin/users/admin")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::FORBIDDEN);
+
+    let res = client.get("http://127.0.0.1:8080/admin/users")
+        .bearer_auth(&admin_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    let users: Vec<serde_json::Value> = res.json().await.unwrap();
+    assert!(users.iter().any(|u| u["username"] == "admin" && u["is_admin"] == true));
+    assert!(users.iter().any(|u| u["username"] == "doomed" && u["is_admin"] == false));
+
+    let res = client.delete("http://127.0.0.1:8080/admin/users/doomed?purge=true")
+        .bearer_auth(&admin_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    let res = client.get("http://127.0.0.1:8080/admin/users")
+        .bearer_auth(&admin_token)
+        .send()
+        .await
+        .unwrap();
+    let users: Vec<serde_json::Value> = res.json().await.unwrap();
+    assert!(!users.iter().any(|u| u["username"] == "doomed"));
+    let res = client.get("http://127.0.0.1:8080/list/shared/doomed")
+        .bearer_auth(&admin_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::NOT_FOUND);
+
+    let res = client.delete("http://127.0.0.1:8080/admin/users/doomed")
+        .bearer_auth(&admin_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::NOT_FOUND);
+
+    // the new account starts with an empty tree
+    let token = login_user("doomed").await;
+    let res = client.get("http://127.0.0.1:8080/list/")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    let files: Vec<serde_json::Value> = res.json().await.unwrap();
+    assert!(!files.iter().any(|f| f["name"] == "doomed.txt"));
+}
+
+// the server of the tests trusts 127.0.0.1 as a proxy (TRUSTED_PROXIES), so every test that makes logins fail
+// uses its own address and the failures of a run don't block the next runs
+fn client_address() -> String {
+    let nanos = std::time::SystemTime::now().duration_since(std::time::UNIX_EPOCH).unwrap().subsec_nanos();
+    format!("2001:db8::{:x}:{:x}", nanos >> 16, nanos & 0xffff)
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_login_rate_limiting() {
+    login_user("throttled").await;
+    let client = reqwest::Client::new();
+    let address = client_address();
+    let login = |password: &'static str| {
+        client.post("http://127.0.0.1:8080/auth/login")
+            .header("X-Forwarded-For", &address)
+            .json(&serde_json::json!({ "username": "throttled", "password": password }))
+            .send()
+    };
+
+    for _ in 0..5 {
+        let res = login("wrong").await.unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::UNAUTHORIZED);
+    }
+
+    // blocked, even with the right password
+    let res = login("password").await.unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+    let retry_after: u64 = res.headers()["retry-after"].to_str().unwrap().parse().unwrap();
+    assert!(retry_after >= 1);
+
+    // the other users are not affected
+    assert!(!login_user("notthrottled").await.is_empty());
+
+    tokio::time::sleep(std::time::Duration::from_secs(retry_after)).await;
+    let res = login("password").await.unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_user_quota() {
+    let admin_token = login_user("admin").await;
+    let token = login_user("quotauser").await;
+    let client = reqwest::Client::new();
+
+    // only the admins can set the quotas
+    let res = client.put("http://127.0.0.1:8080/admin/users/quotauser/quota")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "max_bytes": null, "max_items": null }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::FORBIDDEN);
+
+    let res = client.get("http://127.0.0.1:8080/quota")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    let usage: serde_json::Value = res.json().await.unwrap();
+    assert!(usage["max_bytes"].is_null());
+    let used_items = usage["used_items"].as_u64().unwrap();
+
+    let res = client.put("http://127.0.0.1:8080/admin/users/quotauser/quota")
+        .bearer_auth(&admin_token)
+        .json(&serde_json::json!({ "max_bytes": 100, "max_items": used_items + 2 }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    let res = client.put("http://127.0.0.1:8080/files/quota.txt")
+        .bearer_auth(&token)
+        .body(vec![b'a'; 60])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    // 60 + 60 bytes are more than 100
+    let res = client.put("http://127.0.0.1:8080/files/quota2.txt")
+        .bearer_auth(&token)
+        .body(vec![b'b'; 60])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::INSUFFICIENT_STORAGE);
+
+    // growing the file up to the limit is fine, one byte more is not
+    let res = client.patch("http://127.0.0.1:8080/files/quota.txt?offset=90")
+        .bearer_auth(&token)
+        .body(vec![b'c'; 10])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    let res = client.patch("http://127.0.0.1:8080/files/quota.txt?offset=100")
+        .bearer_auth(&token)
+        .body("d")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::INSUFFICIENT_STORAGE);
+    // an offset that wraps around doesn't slip under the limit
+    let res = client.patch("http://127.0.0.1:8080/files/quota.txt?offset=18446744073709551615")
+        .bearer_auth(&token)
+        .body("d")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+    let res = client.post("http://127.0.0.1:8080/truncate/quota.txt?size=101")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::INSUFFICIENT_STORAGE);
+
+    // one item left
+    let res = client.post("http://127.0.0.1:8080/mkdir/quota_dir")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    let res = client.post("http://127.0.0.1:8080/mkdir/quota_dir2")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::INSUFFICIENT_STORAGE);
+    // a hard link is one more item with the size of the file
+    let res = client.post("http://127.0.0.1:8080/link/quota_link.txt")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "target": "quota.txt" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::INSUFFICIENT_STORAGE);
+
+    // giving a file to another user doesn't free the space of the tree
+    login_user("quotaheir").await;
+    let res = client.post("http://127.0.0.1:8080/metadata/quota.txt")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "owner": "quotaheir" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    let res = client.put("http://127.0.0.1:8080/files/quota2.txt")
+        .bearer_auth(&token)
+        .body("b")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::INSUFFICIENT_STORAGE);
+    let res = client.post("http://127.0.0.1:8080/metadata/quota.txt")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "owner": "quotauser" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    let res = client.get("http://127.0.0.1:8080/admin/users/quotauser/quota")
+        .bearer_auth(&admin_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    let usage: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(usage["used_bytes"], 100);
+    assert_eq!(usage["max_bytes"], 100);
+    assert_eq!(usage["used_items"], used_items + 2);
+
+    // shrinking is always allowed
+    let res = client.post("http://127.0.0.1:8080/truncate/quota.txt?size=10")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    // without limits everything works again
+    let res = client.put("http://127.0.0.1:8080/admin/users/quotauser/quota")
+        .bearer_auth(&admin_token)
+        .json(&serde_json::json!({ "max_bytes": null, "max_items": null }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    let res = client.post("http://127.0.0.1:8080/mkdir/quota_dir2")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    // leave the tree as it was found, so the test can run again
+    for path in ["quota.txt", "quota_dir", "quota_dir2"] {
+        let res = client.delete(format!("http://127.0.0.1:8080/files/{}", path))
+            .bearer_auth(&token)
+            .send()
+            .await
+            .unwrap();
+        assert!(res.status().is_success());
+    }
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_conditional_read_with_etag() {
+    let token = login_user("etaguser").await;
+    let client = reqwest::Client::new();
+
+    client.put("http://127.0.0.1:8080/files/cached.txt")
+        .bearer_auth(&token)
+        .body("first version")
+        .send()
+        .await
+        .unwrap();
+
+    let res = client.get("http://127.0.0.1:8080/files/cached.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert!(res.headers().contains_key("last-modified"));
+    let etag = res.headers()["etag"].to_str().unwrap().to_string();
+
+    // same content: nothing is sent
+    let res = client.get("http://127.0.0.1:8080/files/cached.txt")
+        .bearer_auth(&token)
+        .header("If-None-Match", &etag)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::NOT_MODIFIED);
+    assert_eq!(res.headers()["etag"].to_str().unwrap(), etag);
+    assert!(res.bytes().await.unwrap().is_empty());
+
+    client.put("http://127.0.0.1:8080/files/cached.txt")
+        .bearer_auth(&token)
+        .body("second version")
+        .send()
+        .await
+        .unwrap();
+
+    // the content changed: the new one comes with a new tag
+    let res = client.get("http://127.0.0.1:8080/files/cached.txt")
+        .bearer_auth(&token)
+        .header("If-None-Match", &etag)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_ne!(res.headers()["etag"].to_str().unwrap(), etag);
+    assert_eq!(res.text().await.unwrap(), "second version");
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_exclusive_create() {
+    let token = login_user("excluser").await;
+    let client = reqwest::Client::new();
+
+    let res = client.put("http://127.0.0.1:8080/files/new_file.txt?exclusive=true&permissions=600")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+
+    let res = client.get("http://127.0.0.1:8080/lookup/new_file.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    let info: serde_json::Value = res.json().await.unwrap();
+    assert_eq!(info["size"], 0);
+    assert_eq!(info["permissions"], 0o600);
+
+    // the second creation fails and the file is not replaced
+    let res = client.put("http://127.0.0.1:8080/files/new_file.txt?exclusive=true")
+        .bearer_auth(&token)
+        .body("replaced")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::CONFLICT);
+    let res = client.get("http://127.0.0.1:8080/files/new_file.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.text().await.unwrap(), "");
+
+    let res = client.delete("http://127.0.0.1:8080/files/new_file.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_stable_file_ids() {
+    let token = login_user("iduser").await;
+    let client = reqwest::Client::new();
+    let file_id = |info: &serde_json::Value| info["file_id"].as_i64().unwrap();
+
+    for (path, body) in [("first.txt", "one"), ("second.txt", "two")] {
+        let res = client.put(format!("http://127.0.0.1:8080/files/{}", path))
+            .bearer_auth(&token)
+            .body(body)
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::OK);
+    }
+    let lookup = |path: &str| {
+        client.get(format!("http://127.0.0.1:8080/lookup/{}", path)).bearer_auth(&token).send()
+    };
+    let first = file_id(&lookup("first.txt").await.unwrap().json().await.unwrap());
+    let second = file_id(&lookup("second.txt").await.unwrap().json().await.unwrap());
+    assert_ne!(first, second);
+
+    // the listing has the same ids of lookup
+    let list: Vec<serde_json::Value> = client.get("http://127.0.0.1:8080/list/")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let listed = list.iter().find(|f| f["name"] == "first.txt").unwrap();
+    assert_eq!(file_id(listed), first);
+
+    // a rename keeps the id
+    let res = client.post("http://127.0.0.1:8080/rename")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "from": "first.txt", "to": "renamed.txt" }))
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    assert_eq!(file_id(&lookup("renamed.txt").await.unwrap().json().await.unwrap()), first);
+
+    // all the names of a hard link have the same id
+    let res = client.post("http://127.0.0.1:8080/link/other_name.txt")
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "target": "renamed.txt" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_eq!(file_id(&lookup("other_name.txt").await.unwrap().json().await.unwrap()), first);
+
+    // the link keeps the id when the name that had it is removed
+    let res = client.delete("http://127.0.0.1:8080/files/renamed.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    assert_eq!(file_id(&lookup("other_name.txt").await.unwrap().json().await.unwrap()), first);
+
+    // a file created again after a delete is a new item
+    let res = client.delete("http://127.0.0.1:8080/files/second.txt")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert!(res.status().is_success());
+    let res = client.put("http://127.0.0.1:8080/files/second.txt")
+        .bearer_auth(&token)
+        .body("again")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+    assert_ne!(file_id(&lookup("second.txt").await.unwrap().json().await.unwrap()), second);
+
+    for path in ["other_name.txt", "second.txt"] {
+        let res = client.delete(format!("http://127.0.0.1:8080/files/{}", path))
+            .bearer_auth(&token)
+            .send()
+            .await
+            .unwrap();
+        assert!(res.status().is_success());
+    }
+}
+
+// the server of the tests runs with ADMIN_USERS=admin and ADMIN_PASSWORD=password
+#[tokio::test]
+#[serial_test::serial]
+async fn test_admin_name_reserved() {
+    let client = reqwest::Client::new();
+    let res = client.post("http://127.0.0.1:8080/auth/register")
+        .json(&serde_json::json!({ "username": "admin", "password": "takeover" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::BAD_REQUEST);
+
+    // the admin created at startup keeps its password
+    let res = client.post("http://127.0.0.1:8080/auth/login")
+        .json(&serde_json::json!({ "username": "admin", "password": "takeover" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::UNAUTHORIZED);
+    let admin_token = login_user("admin").await;
+
+    // a normal registration never gives the admin role
+    let token = login_user("notadmin").await;
+    let res = client.get("http://127.0.0.1:8080/admin/users")
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::FORBIDDEN);
+    let res = client.get("http://127.0.0.1:8080/admin/users")
+        .bearer_auth(&admin_token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_login_rate_limiting_per_address() {
+    login_user("fromaddress").await;
+    let client = reqwest::Client::new();
+    let address = client_address();
+    let login = |username: String, password: &'static str, address: String| {
+        client.post("http://127.0.0.1:8080/auth/login")
+            .header("X-Forwarded-For", address)
+            .json(&serde_json::json!({ "username": username, "password": password }))
+            .send()
+    };
+
+    // 20 different usernames from the same address, each one below the limit of the username
+    for i in 0..20 {
+        let res = login(format!("guess{}", i), "wrong", address.clone()).await.unwrap();
+        assert_eq!(res.status(), reqwest::StatusCode::UNAUTHORIZED);
+    }
+
+    // the address is blocked, also for a right password; the other addresses are not
+    let res = login("fromaddress".to_string(), "password", address.clone()).await.unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::TOO_MANY_REQUESTS);
+    assert!(res.headers().contains_key("retry-after"));
+    let res = login("fromaddress".to_string(), "password", client_address()).await.unwrap();
+    assert_eq!(res.status(), reqwest::StatusCode::OK);
+}
+
+#[tokio::test]
+#[serial_test::serial]
+async fn test_login_burst_checks_few_passwords() {
+    login_user("bursted").await;
+    let client = reqwest::Client::new();
+    let address = client_address();
+
+    // the attempts are reserved before bcrypt: of a concurrent burst at most 5 get to the password check
+    let attempts = (0..30).map(|_| {
+        client.post("http://127.0.0.1:8080/auth/login")
+            .header("X-Forwarded-For", &address)
+            .json(&serde_json::json!({ "username": "bursted", "password": "wrong" }))
+            .send()
+    });
+    let statuses: Vec<reqwest::StatusCode> = futures::future::join_all(attempts).await
+        .into_iter()
+        .map(|res| res.unwrap().status())
+        .collect();
+    let checked = statuses.iter().filter(|s| **s == reqwest::StatusCode::UNAUTHORIZED).count();
+    assert!(checked <= 5, "{} passwords checked", checked);
+    assert!(statuses.iter().all(|s| *s == reqwest::StatusCode::UNAUTHORIZED || *s == reqwest::StatusCode::TOO_MANY_REQUESTS));
+}
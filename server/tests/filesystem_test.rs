@@ -0,0 +1,219 @@
+use server::FileSystem;
+use rusqlite::{params, Connection};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+// create an empty directory used as root of the filesystem under test
+fn setup_dir() -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("remote-fs-test-{}", uuid::Uuid::new_v4()));
+    fs::create_dir_all(&dir).unwrap();
+    dir
+}
+
+// in-memory database with the same METADATA and group tables used by the server
+fn setup_db() -> Arc<Mutex<Connection>> {
+    let conn = Connection::open_in_memory().unwrap();
+    conn.execute_batch(
+        "CREATE TABLE USER (
+            User_ID INTEGER PRIMARY KEY AUTOINCREMENT,
+            Username TEXT NOT NULL UNIQUE,
+            Password TEXT NOT NULL,
+            Quota_Bytes INTEGER,
+            Quota_Items INTEGER
+        );
+        CREATE TABLE METADATA (
+            file_id INTEGER PRIMARY KEY AUTOINCREMENT,
+            path TEXT NOT NULL,
+            user_id INTEGER NOT NULL,
+            user_permissions INTEGER NOT NULL,
+            group_permissions INTEGER NOT NULL,
+            others_permissions INTEGER NOT NULL,
+            size INTEGER NOT NULL DEFAULT 0,
+            created_at TEXT NOT NULL,
+            last_modified TEXT NOT NULL,
+            type INTEGER NOT NULL,
+            group_id INTEGER,
+            owner_id INTEGER,
+            last_accessed TEXT,
+            last_changed TEXT,
+            inode_id INTEGER
+        );
+        CREATE TABLE GROUPS (
+            Group_ID INTEGER PRIMARY KEY AUTOINCREMENT,
+            Name TEXT NOT NULL UNIQUE
+        );
+        CREATE TABLE GROUP_MEMBER (
+            Group_ID INTEGER NOT NULL,
+            User_ID INTEGER NOT NULL,
+            PRIMARY KEY (Group_ID, User_ID)
+        );"
+    ).unwrap();
+    Arc::new(Mutex::new(conn))
+}
+
+fn load_fs(dir: &Path, db: &Arc<Mutex<Connection>>) -> FileSystem {
+    let mut fs = FileSystem::from_file_system(dir.to_str().unwrap());
+    fs.set_side_effects(true);
+    fs.set_database(db.clone());
+    fs
+}
+
+fn metadata_paths(db: &Arc<Mutex<Connection>>, user_id: i64) -> Vec<String> {
+    let conn = db.lock().unwrap();
+    let mut stmt = conn.prepare("SELECT path FROM METADATA WHERE user_id = ?1 ORDER BY path").unwrap();
+    let rows = stmt.query_map(params![user_id], |row| row.get::<_, String>(0)).unwrap();
+    rows.map(|r| r.unwrap()).collect()
+}
+
+// TESTS ON
+// reconciliation between disk, tree and METADATA
+
+#[test]
+fn test_reconcile_inserts_untracked_items() {
+    let dir = setup_dir();
+    fs::create_dir_all(dir.join("docs/old")).unwrap();
+    fs::write(dir.join("docs/notes.txt"), "some notes").unwrap();
+
+    let db = setup_db();
+    let mut fs = load_fs(&dir, &db);
+    let report = fs.reconcile(1).unwrap();
+
+    assert_eq!(report.inserted.len(), 3);
+    assert_eq!(metadata_paths(&db, 1), vec!["docs", "docs/notes.txt", "docs/old"]);
+
+    // the pre-existing folder is now usable with the default permissions
+    let info = fs.lookup_item("docs", 1).unwrap();
+    assert_eq!(info.permissions, 0o755);
+    let info = fs.lookup_item("docs/notes.txt", 1).unwrap();
+    assert_eq!(info.permissions, 0o644);
+    assert_eq!(info.size, 10);
+
+    // a second pass finds nothing to do
+    assert!(fs.reconcile(1).unwrap().is_clean());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_reconcile_prunes_and_updates() {
+    let dir = setup_dir();
+    fs::write(dir.join("keep.txt"), "abc").unwrap();
+    fs::write(dir.join("gone.txt"), "abc").unwrap();
+
+    let db = setup_db();
+    let mut fs = load_fs(&dir, &db);
+    fs.reconcile(1).unwrap();
+
+    // changes made behind the back of the server
+    fs::remove_file(dir.join("gone.txt")).unwrap();
+    fs::write(dir.join("keep.txt"), "abcdef").unwrap();
+
+    let report = fs.reconcile(1).unwrap();
+    assert_eq!(report.pruned, vec!["gone.txt"]);
+    assert_eq!(report.updated, vec!["keep.txt"]);
+    assert_eq!(report.tree_removed, vec!["gone.txt"]);
+    assert_eq!(metadata_paths(&db, 1), vec!["keep.txt"]);
+    assert_eq!(fs.lookup_item("keep.txt", 1).unwrap().size, 6);
+    assert!(fs.find("/gone.txt").is_none());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_reconcile_skips_upload_temp_files() {
+    let dir = setup_dir();
+    fs::write(dir.join("photo.jpg"), "done").unwrap();
+    fs::write(dir.join(".photo.jpg.upload-1234"), "in progress").unwrap();
+
+    let db = setup_db();
+    let mut fs = load_fs(&dir, &db);
+    let report = fs.reconcile(1).unwrap();
+
+    assert_eq!(report.inserted, vec!["photo.jpg"]);
+    assert_eq!(metadata_paths(&db, 1), vec!["photo.jpg"]);
+    assert!(fs.find("/.photo.jpg.upload-1234").is_none());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_reconcile_tracks_symlinks() {
+    let dir = setup_dir();
+    fs::write(dir.join("target.txt"), "abc").unwrap();
+    std::os::unix::fs::symlink("target.txt", dir.join("link.txt")).unwrap();
+
+    let db = setup_db();
+    let mut fs = load_fs(&dir, &db);
+    fs.reconcile(1).unwrap();
+
+    assert_eq!(metadata_paths(&db, 1), vec!["link.txt", "target.txt"]);
+    let info = fs.lookup_item("link.txt", 1).unwrap();
+    assert!(info.is_symlink);
+    assert_eq!(fs.read_link("/link.txt").unwrap(), "target.txt");
+    assert_eq!(fs.read_file("/link.txt").unwrap(), b"abc");
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_write_through_symlink_updates_target() {
+    let dir = setup_dir();
+    fs::write(dir.join("target.txt"), "abc").unwrap();
+    std::os::unix::fs::symlink("target.txt", dir.join("link.txt")).unwrap();
+
+    let db = setup_db();
+    let mut fs = load_fs(&dir, &db);
+    fs.reconcile(1).unwrap();
+
+    fs.write_at("/link.txt", 3, b"def", 1, "644").unwrap();
+    assert_eq!(fs.lookup_item("target.txt", 1).unwrap().size, 6);
+    // the link keeps the size of its own target path
+    assert_eq!(fs.lookup_item("link.txt", 1).unwrap().size, "target.txt".len() as i64);
+    assert!(fs.reconcile(1).unwrap().is_clean());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn test_load_dangling_symlink() {
+    let dir = setup_dir();
+    std::os::unix::fs::symlink("missing.txt", dir.join("dangling.txt")).unwrap();
+
+    let db = setup_db();
+    let mut fs = load_fs(&dir, &db);
+    fs.reconcile(1).unwrap();
+
+    assert_eq!(fs.read_link("/dangling.txt").unwrap(), "missing.txt");
+    assert!(fs.read_file("/dangling.txt").is_err());
+
+    fs::remove_dir_all(&dir).unwrap();
+}
+
+// TESTS ON
+// access and modification times
+
+#[test]
+fn test_set_times_survives_reconcile() {
+    let dir = setup_dir();
+    fs::write(dir.join("build.o"), "obj").unwrap();
+
+    let db = setup_db();
+    let mut fs = load_fs(&dir, &db);
+    fs.reconcile(1).unwrap();
+
+    let mtime = chrono::DateTime::parse_from_rfc3339("2020-01-02T03:04:05Z").unwrap().with_timezone(&chrono::Utc);
+    fs.set_times("/build.o", Some(mtime), Some(mtime), 1, false).unwrap();
+
+    // the time is also on disk, so the next pass doesn't bring back the old one
+    let on_disk = fs::metadata(dir.join("build.o")).unwrap().modified().unwrap();
+    assert_eq!(chrono::DateTime::<chrono::Utc>::from(on_disk), mtime);
+    assert!(fs.reconcile(1).unwrap().is_clean());
+
+    let last_modified: String = db.lock().unwrap()
+        .query_row("SELECT last_modified FROM METADATA WHERE path = 'build.o'", [], |row| row.get(0))
+        .unwrap();
+    assert_eq!(chrono::DateTime::parse_from_rfc3339(&last_modified).unwrap(), mtime);
+
+    fs::remove_dir_all(&dir).unwrap();
+}